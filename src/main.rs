@@ -1,32 +1,92 @@
 #![allow(clippy::too_many_lines)]
 #![allow(clippy::used_underscore_binding)]
 
-use anyhow::{format_err, Error};
+use async_trait::async_trait;
 use dioxus::{
     core::exports::futures_channel::oneshot::{channel, Sender},
     prelude::{
         dioxus_elements, format_args_f, rsx, use_future, use_state, Element,
-        LazyNodes, NodeFactory, Scope, VNode,
+        LazyNodes, NodeFactory, Scope, UseFutureState, VNode,
     },
 };
+use gloo_timers::{callback::Timeout, future::TimeoutFuture};
 use http::method::Method;
 use im_rc::HashMap;
-use log::debug;
+use lazy_static::lazy_static;
+use log::{debug, error};
 use serde::Deserialize;
-use stack_string::StackString;
-use time::UtcOffset;
-use url::Url;
+use stack_string::{format_sstr, StackString};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+use time::{macros::format_description, Duration, OffsetDateTime, UtcOffset};
+use url::{form_urlencoded, Url};
 use wasm_bindgen::{closure::Closure, JsCast, JsValue};
 use wasm_bindgen_futures::JsFuture;
-use web_sys::{Request, RequestInit, Response};
+use web_sys::{window, PositionOptions, Request, RequestInit, Response, Window};
 
 use weather_util_rust::{
     latitude::Latitude, longitude::Longitude, weather_api::WeatherLocation,
-    weather_data::WeatherData, weather_forecast::WeatherForecast,
+    weather_data::WeatherData, ApiStringType,
+};
+
+use weather_app_core::{
+    alerts::{active_alerts, alert_banner, OneCallAlerts, WeatherAlert},
+    compare::{compare_chart, DailySeries},
+    components::{country_info, page_layout, weather_card, ForecastView},
+    current::CurrentWeather,
+    endpoint::{resolve_geocode_path, resolve_seconds, ApiEndpoint, EndpointSource},
+    entry::{RequestState, WeatherEntry},
+    error::{GeolocationError, ParseError, WeatherError},
+    hourly::HourlyForecast,
+    location::{get_parameters, location_to_search, parse_location},
+    provider::{fetch_entries, fetch_entry, suggest, GeoLocation, WeatherProvider},
+    route::AppRoute,
+    storage::{HistoryEntry, StoredState},
+    units::{PrecipitationUnit, SpeedUnit, TemperatureUnit, UnitSystem},
 };
 
+lazy_static! {
+    static ref API: ApiEndpoint = resolve_api_endpoint();
+    static ref GEOCODE_PATH: StackString = resolve_web_geocode_path();
+    static ref CACHE_TTL: Duration = Duration::seconds(
+        resolve_web_seconds(
+            "cache_ttl_seconds",
+            get_web_config().cache_ttl_seconds,
+            option_env!("WEATHER_CACHE_TTL_SECONDS"),
+            DEFAULT_CACHE_TTL_SECONDS,
+        )
+        .into(),
+    );
+    static ref REFRESH_INTERVAL_SECONDS: u32 = resolve_web_seconds(
+        "refresh_interval_seconds",
+        get_web_config().refresh_interval_seconds,
+        option_env!("WEATHER_REFRESH_INTERVAL_SECONDS"),
+        DEFAULT_REFRESH_INTERVAL_SECONDS,
+    );
+}
+
 static DEFAULT_STR: &str = "11106";
 static API_ENDPOINT: &str = "https://cloud.ddboline.net/weather/";
+static DEFAULT_CACHE_TTL_SECONDS: u32 = 600;
+static DEFAULT_REFRESH_INTERVAL_SECONDS: u32 = 600;
+static STORAGE_KEY: &str = "weather-app-state";
+static LEGACY_HISTORY_KEY: &str = "history";
+static IP_LOCATION_URL: &str = "https://ipwhois.app/json/";
+static GEOLOCATION_TIMEOUT_MS: u32 = 10_000;
+static GEOLOCATION_MAX_AGE_MS: u32 = 300_000;
+static GEOLOCATION_PROMPT_TIMEOUT_MS: u32 = 30_000;
+static SUGGEST_DEBOUNCE_MS: u32 = 300;
+static CACHED_AT_HEADER: &str = "x-sw-cached-at";
+
+#[derive(Default, Deserialize)]
+struct WebConfig {
+    api_endpoint: Option<String>,
+    geocode_path: Option<String>,
+    cache_ttl_seconds: Option<u32>,
+    refresh_interval_seconds: Option<u32>,
+}
 
 #[derive(Copy, Clone, Default, Deserialize, Debug)]
 struct Location {
@@ -34,12 +94,6 @@ struct Location {
     longitude: Longitude,
 }
 
-#[derive(Clone, Debug)]
-struct WeatherEntry {
-    weather: Option<WeatherData>,
-    forecast: Option<WeatherForecast>,
-}
-
 fn main() {
     wasm_logger::init(wasm_logger::Config::default());
     debug!("{:?}", WeatherData::default());
@@ -49,421 +103,1148 @@ fn main() {
 fn app(cx: Scope<()>) -> Element {
     let (send, recv) = channel();
 
-    let default_cache: HashMap<WeatherLocation, WeatherEntry> = HashMap::new();
+    let stored = cx.use_hook(|_| get_stored_state());
+    let route = cx.use_hook(|_| get_route());
+    let has_route_location = route.location.is_some();
+
+    let default_cache: HashMap<WeatherLocation, WeatherEntry> = stored
+        .cache
+        .iter()
+        .map(|(s, entry)| (get_parameters(s), entry.clone()))
+        .collect();
     let mut default_location_cache: HashMap<String, WeatherLocation> = HashMap::new();
     default_location_cache.insert(DEFAULT_STR.into(), get_parameters(DEFAULT_STR));
+    for s in stored
+        .history
+        .iter()
+        .map(|h| &h.search)
+        .chain(stored.last_location.iter())
+        .chain(route.location.iter())
+    {
+        default_location_cache.insert(s.as_str().into(), get_parameters(s));
+    }
+    let default_location = route.get_location().unwrap_or_else(|| {
+        stored
+            .last_location
+            .as_ref()
+            .map_or_else(|| get_parameters(DEFAULT_STR), |s| get_parameters(s))
+    });
+    let mut default_history = if stored.history.is_empty() {
+        vec![HistoryEntry::new(DEFAULT_STR)]
+    } else {
+        stored.history.clone()
+    };
+    // A shared link is added to the history like a search
+    if let Some(search) = &route.location {
+        if !default_history.iter().any(|h| &h.search == search) {
+            default_history.push(HistoryEntry::new(search));
+        }
+    }
+    let default_favourites = stored.favourites.clone();
+    let default_dismissed_alerts = stored.dismissed_alerts.clone();
 
     let (cache, set_cache) = use_state(&cx, || default_cache).split();
     let (location_cache, set_location_cache) = use_state(&cx, || default_location_cache).split();
-    let (location, set_location) = use_state(&cx, || get_parameters(DEFAULT_STR)).split();
-    let (weather, set_weather) = use_state(&cx, WeatherData::default).split();
-    let (forecast, set_forecast) = use_state(&cx, WeatherForecast::default).split();
+    let (location, set_location) = use_state(&cx, || default_location).split();
     let (draft, set_draft) = use_state(&cx, String::new).split();
-    let (search_history, set_search_history) =
-        use_state(&cx, || vec![StackString::from(DEFAULT_STR)]).split();
+    let (search_history, set_search_history) = use_state(&cx, || default_history).split();
+    let (favourites, set_favourites) = use_state(&cx, || default_favourites).split();
+    let (dismissed_alerts, set_dismissed_alerts) =
+        use_state(&cx, || default_dismissed_alerts).split();
+    let (units, set_units) = use_state(&cx, || {
+        route.get_units(get_unit_system().unwrap_or_default())
+    })
+    .split();
+    let (refresh_tick, set_refresh_tick) = use_state(&cx, || 0_u64).split();
+    let (forecast_view, set_forecast_view) = use_state(&cx, || route.view.unwrap_or_default()).split();
+    let (comparing, set_comparing) = use_state(&cx, || false).split();
+    let (compared, set_compared) = use_state(&cx, Vec::<StackString>::new).split();
+
+    let (location_error, set_location_error) = use_state(&cx, || None).split();
+    let (locate_requests, set_locate_requests) = use_state(&cx, || 0_u32).split();
+
+    cx.use_hook(|_| {
+        listen_popstate(
+            set_location.setter(),
+            set_units.setter(),
+            set_forecast_view.setter(),
+        )
+        .unwrap_or_else(|e| debug!("popstate {e:?}"));
+    });
+
+    use_future(
+        &cx,
+        (location, units, forecast_view),
+        |(location, units, view)| async move {
+            let route = AppRoute::new(&location, units, view);
+            if let Err(e) = push_route(&route) {
+                debug!("push route {e:?}");
+            }
+        },
+    );
 
-    let location_future = use_future(&cx, (), |_| async move {
-        if update_location(send).is_ok() {
-            if let Ok(location) = recv.await {
-                return Some(location);
+    // The last location only seeds the first view until the position is
+    // known, but a shared link shows the location it names unless the user
+    // asks for their own
+    let location_future = use_future(&cx, locate_requests, |requests| async move {
+        if requests == 0 && has_route_location {
+            return (None, None);
+        }
+        let result = match update_location(send) {
+            Ok(()) => recv.await.unwrap_or(Err(GeolocationError::PositionUnavailable)),
+            Err(e) => {
+                debug!("geolocation {e:?}");
+                Err(GeolocationError::Unsupported)
+            }
+        };
+        match result {
+            Ok(location) => (Some(location), None),
+            Err(e) => {
+                error!("geolocation {e}");
+                let location = match get_location_from_ip().await {
+                    Ok(location) => Some(location),
+                    Err(e) => {
+                        error!("ip location {e}");
+                        None
+                    }
+                };
+                (location, Some(e))
             }
         }
-        None
     });
 
     let weather_future = use_future(&cx, location, |l| {
         let entry_opt = cache.get(&l).cloned();
         async move {
-            if let Some(entry) = entry_opt {
-                entry
-            } else {
-                get_weather_data_forecast(&l).await
-            }
+            let entry = match entry_opt {
+                Some(entry) if entry.error.is_none() && !entry.is_stale(get_cache_ttl()) => entry,
+                _ => get_weather_data_forecast(&l).await,
+            };
+            (l, entry)
         }
     });
 
-    cx.render({
-        if let Some(Some(location)) = location_future.value() {
-            set_location.modify(|_| get_parameters(&format!("{},{}", location.latitude, location.longitude)));
-            set_location.needs_update();
+    let refresh_future = use_future(&cx, (location, refresh_tick), |(l, tick)| async move {
+        TimeoutFuture::new(get_refresh_interval_seconds() * 1000).await;
+        debug!("refresh {l:?}");
+        let entry = get_weather_data_forecast(&l).await;
+        (tick + 1, l, entry)
+    });
+
+    let favourites_future = use_future(&cx, favourites, |favourites| {
+        let locations = uncached_locations(cache, &favourites);
+        async move { fetch_entries(&FetchProvider::new(&API.url), &locations).await }
+    });
+
+    let compare_future = use_future(&cx, compared, |compared| {
+        let locations = uncached_locations(cache, &compared);
+        async move { fetch_entries(&FetchProvider::new(&API.url), &locations).await }
+    });
+
+    // Each keystroke restarts this future; only the latest one is still
+    // current when the delay is up, so typing quickly makes one request
+    let debounce = cx.use_hook(|_| Rc::new(Cell::new(0_u64))).clone();
+    let suggest_future = use_future(&cx, draft, |q| async move {
+        let generation = debounce.get() + 1;
+        debounce.set(generation);
+        TimeoutFuture::new(SUGGEST_DEBOUNCE_MS).await;
+        if debounce.get() != generation {
+            return (q, Vec::new());
         }
-        if let Some(entry) = weather_future.value() {
-            set_cache.modify(|c| {
-                let new_cache = c.update(location.clone(), entry.clone());
-                if let Some(WeatherEntry{weather, forecast}) = new_cache.get(location) {
-                    if let Some(weather) = weather {
-                        debug!("weather_future {location:?}");
-                        set_weather.modify(|_| weather.clone());
-                        set_weather.needs_update();
-                    }
-                    if let Some(forecast) = forecast {
-                        debug!("forecast_future {location:?}");
-                        set_forecast.modify(|_| forecast.clone());
-                        set_forecast.needs_update();
-                    }
-                }
-                new_cache
+        let places = suggest(&FetchProvider::new(&API.url), &q)
+            .await
+            .unwrap_or_else(|e| {
+                error!("suggest {q} {e}");
+                Vec::new()
             });
+        (q, places)
+    });
+
+    use_future(
+        &cx,
+        (search_history, favourites, location, cache, dismissed_alerts),
+        |(history, favourites, location, cache, dismissed_alerts)| async move {
+            let cache = if persist_cache() {
+                cache
+                    .iter()
+                    .filter(|(_, e)| e.weather.is_some() && e.forecast.is_some())
+                    .map(|(l, e)| (location_to_search(l), e.clone()))
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            let state = StoredState {
+                history,
+                favourites,
+                last_location: Some(location_to_search(&location)),
+                cache,
+                dismissed_alerts,
+                ..StoredState::default()
+            };
+            if let Err(e) = set_stored_state(&state) {
+                error!("Failed to save state {e:?}");
+            }
+        },
+    );
+
+    cx.render({
+        let relabel_history = |l: &WeatherLocation, weather: &WeatherData| {
+            let relabelled: Vec<_> = search_history.iter().map(|h| h.relabel(l, weather)).collect();
+            if relabelled.iter().any(Option::is_some) {
+                set_search_history.modify(|sh| {
+                    sh.iter()
+                        .zip(relabelled)
+                        .map(|(h, r)| r.unwrap_or_else(|| h.clone()))
+                        .collect()
+                });
+                set_search_history.needs_update();
+            }
+        };
+        if let Some((found, e)) = location_future.value() {
+            if let Some(found) = found {
+                let new_location = get_parameters(&format!("{},{}", found.latitude, found.longitude));
+                let entry = HistoryEntry::from_location(&new_location, None);
+                set_search_history.modify(|sh| {
+                    let mut v: Vec<HistoryEntry> = sh.iter().filter(|h| h.search != entry.search).cloned().collect();
+                    v.push(entry);
+                    v
+                });
+                set_search_history.needs_update();
+                set_location.modify(|_| new_location);
+                set_location.needs_update();
+            }
+            let fallback = found.is_some();
+            set_location_error.set(e.map(|e| (e, fallback)));
+            location_future.clear();
+        }
+        if let Some((l, entry)) = weather_future.value() {
+            if let Some(weather) = &entry.weather {
+                relabel_history(l, weather);
+            }
+            debug!("weather_future {l:?}");
+            set_cache.modify(|c| c.update(l.clone(), entry.clone().or_previous(c.get(l))));
             set_cache.needs_update();
         }
-        let country_info_element = country_info(weather);
-        let country_data_element = country_data(weather);
-        let week_weather_element = week_weather(forecast);
-
-        rsx!(
-            link { rel: "stylesheet", href: "https://unpkg.com/tailwindcss@^2.0/dist/tailwind.min.css" },
-            div { class: "mx-auto p-4 bg-gray-100 h-screen flex justify-center",
-                div { class: "flex items-center justify-center flex-col",
-                    div {
-                        div { class: "inline-flex flex-col justify-center relative text-gray-500",
-                            div { class: "relative",
-                                input { class: "p-2 pl-8 rounded border border-gray-200 bg-gray-200 focus:bg-white focus:outline-none focus:ring-2 focus:ring-yellow-600 focus:border-transparent",
-                                    placeholder: "search...",
-                                    "type": "text",
-                                    value: "{draft}",
-                                    oninput: move |evt| {
-                                        let msg = evt.value.as_str();
-                                        set_draft.modify(|_| msg.into());
-                                        set_draft.needs_update();
-                                        let new_location = location_cache.get(msg).map_or_else(
-                                            || {
-                                                let l = get_parameters(msg);
-                                                set_location_cache.modify(|lc| lc.update(msg.into(), l.clone()));
-                                                l
-                                            }, Clone::clone
-                                        );
-                                        if let Some(WeatherEntry{weather, forecast}) = cache.get(&new_location) {
-                                            if let Some(weather) = weather {
-                                                debug!("weather_oninput {location:?}");
-                                                set_weather.modify(|_| weather.clone());
-                                                set_weather.needs_update();
-                                            }
-                                            if let Some(forecast) = forecast {
-                                                debug!("forecast_oninput {location:?}");
-                                                set_forecast.modify(|_| forecast.clone());
-                                                set_forecast.needs_update();
-                                            }
-                                            set_location.modify(|_| new_location);
-                                            set_location.needs_update();
-                                        }
-                                    },
-                                    onkeydown: move |evt| {
-                                        let new_location = location_cache.get(draft).map_or_else(
-                                            || {
-                                                let l = get_parameters(draft);
-                                                set_location_cache.modify(|lc| lc.update(draft.into(), l.clone()));
-                                                l
-                                            }, Clone::clone
-                                        );
-                                        if let Some(WeatherEntry{weather, forecast}) = cache.get(&new_location) {
-                                            if let Some(weather) = weather {
-                                                debug!("weather_onkeydown {location:?}");
-                                                set_weather.modify(|_| weather.clone());
-                                                set_weather.needs_update();
-                                            }
-                                            if let Some(forecast) = forecast {
-                                                debug!("forecast_onkeydown {location:?}");
-                                                set_forecast.modify(|_| forecast.clone());
-                                                set_forecast.needs_update();
-                                            }
-                                        }
-                                        if evt.key == "Enter" {
-                                            set_draft.modify(|_| String::new());
-                                            set_draft.needs_update();
-                                            set_search_history.modify(|sh| {
-                                                let mut v: Vec<StackString> = sh.iter().filter(|s| s.as_str() != draft.as_str()).cloned().collect();
-                                                v.push(draft.into());
-                                                v
-                                            });
-                                            set_location.modify(|_| new_location);
-                                            set_location.needs_update();
-                                        }
-                                    },
-                                }
-                                svg { class: "w-4 h-4 absolute left-2.5 top-3.5",
-                                    "viewBox": "0 0 24 24",
-                                    fill: "none",
-                                    stroke: "currentColor",
-                                    xmlns: "https://www.w3.org/2000/svg",
-                                    path {
-                                        d: "M21 21l-6-6m2-5a7 7 0 11-14 0 7 7 0 0114 0z",
-                                        "stroke-linejoin": "round",
-                                        "stroke-linecap": "round",
-                                        "stroke-width": "2",
-                                    }
-                                }
-                            }
-                        }
-                        select { class: "bg-white border border-gray-100 w-full mt-2",
-                            id: "history-selector",
-                            onchange: move |x| {
-                                let s = x.data.value.as_str();
-                                let new_location = location_cache.get(s).map_or_else(|| {
-                                    let l = get_parameters(s);
-                                    set_location_cache.modify(|lc| lc.update(s.into(), l.clone()));
-                                    set_location_cache.needs_update();
-                                    set_search_history.modify(|sh| {
-                                        let mut v: Vec<StackString> = sh.iter().filter(|x| x.as_str() != s).cloned().collect();
-                                        v.push(s.into());
-                                        v
-                                    });
-                                    set_search_history.needs_update();
-                                    l
-                                }, Clone::clone);
-                                debug!("{new_location:?}");
-                                if let Some(WeatherEntry{weather, forecast}) = cache.get(&new_location) {
-                                    if let Some(weather) = weather {
-                                        debug!("weather {new_location:?}");
-                                        set_weather.modify(|_| weather.clone());
-                                        set_weather.needs_update();
-                                    }
-                                    if let Some(forecast) = forecast {
-                                        debug!("forecast {new_location:?}");
-                                        set_forecast.modify(|_| forecast.clone());
-                                        set_forecast.needs_update();
-                                    }
-                                }
-                                set_location.modify(|_| new_location);
-                                set_location.needs_update();
-                            },
-                            {
-                                search_history.iter().rev().map(|s| {
-                                    let selected = location_cache.contains_key(s.as_str());
-                                    rsx! {
-                                        option { class: "pl-8 pr-2 py-1 border-b-2 border-gray-100 relative cursor-pointer hover:bg-yellow-50 hover:text-gray-900",
-                                            key: "search-history-key-{s}",
-                                            value: "{s}",
-                                            selected: "{selected}",
-                                            "{s}"
-                                        }
-                                    }
-                                })
-                            }
-                        }
-                    }
-                    div { class: "flex flex-wrap w-full px-2",
-                        div { class: "bg-gray-900 text-white relative min-w-0 break-words rounded-lg overflow-hidden shadow-sm mb-4 w-full bg-white dark:bg-gray-600",
-                            div { class: "px-6 py-6 relative",
-                                country_info_element,
-                                country_data_element,
-                            }
-                            week_weather_element,
+        for entries_future in [favourites_future, compare_future] {
+            if let Some(entries) = entries_future.value() {
+                if !entries.is_empty() {
+                    set_cache.modify(|c| {
+                        entries.iter().fold(c.clone(), |c, (l, entry)| {
+                            let entry = entry.clone().or_previous(c.get(l));
+                            c.update(l.clone(), entry)
+                        })
+                    });
+                    set_cache.needs_update();
+                }
+                entries_future.clear();
+            }
+        }
+        if let Some((tick, l, entry)) = refresh_future.value() {
+            if tick > refresh_tick {
+                set_cache.modify(|c| c.update(l.clone(), entry.clone().or_previous(c.get(l))));
+                set_cache.needs_update();
+                if let Some(weather) = &entry.weather {
+                    relabel_history(l, weather);
+                }
+                set_refresh_tick.modify(|_| *tick);
+                set_refresh_tick.needs_update();
+            }
+        }
+        let error_element = cache.get(location).and_then(|entry| entry.error.as_ref()).map(|e| {
+            rsx! {
+                div { class: "flex w-full px-2",
+                    div { class: "bg-red-100 border border-red-400 text-red-700 px-4 py-3 rounded relative mb-4 w-full flex justify-between items-center",
+                        span { "{e}" }
+                        button { class: "ml-4 px-2 py-1 rounded bg-red-700 text-white",
+                            onclick: move |_| weather_future.restart(),
+                            "Retry"
                         }
                     }
                 }
             }
-        )
-    })
-}
-
-fn country_data<'a>(weather: &'a WeatherData) -> LazyNodes<'a, 'a> {
-    let temp = weather.main.temp.fahrenheit();
-    let feels = weather.main.feels_like.fahrenheit();
-    let min = weather.main.temp_min.fahrenheit();
-    let max = weather.main.temp_max.fahrenheit();
-
-    rsx!(
-        div { class: "block sm:flex justify-between items-center flex-wrap",
-            div { class: "w-full sm:w-1/2",
-                div { class: "flex mb-2 justify-between items-center",
-                    span { "Temp" }
-                    small { class: "px-2 inline-block", "{temp:0.2}°F" }
+        });
+        let pending = matches!(
+            weather_future.state(),
+            UseFutureState::Pending | UseFutureState::Reloading(_)
+        );
+        let location_error_element = location_error.map(|(e, fallback)| {
+            let message = if fallback {
+                "showing an approximate location"
+            } else {
+                "search for a location below"
+            };
+            rsx! {
+                div { class: "flex w-full px-2",
+                    div { class: "bg-yellow-100 border border-yellow-400 text-yellow-700 px-4 py-3 rounded mb-4 w-full flex justify-between items-center",
+                        span { "Location unavailable ({e}), {message}" }
+                        button { class: "ml-4 px-2 py-1 rounded bg-yellow-700 text-white",
+                            onclick: move |_| set_location_error.set(None),
+                            "Dismiss"
+                        }
+                    }
                 }
             }
-            div { class: "w-full sm:w-1/2",
-                div { class: "flex mb-2 justify-between items-center",
-                    span { "Feels like" }
-                    small { class: "px-2 inline-block", "{feels:0.2}°F" }
+        });
+        let offline_element = cache.get(location).and_then(WeatherEntry::offline_since).map(|fetched_at| {
+            let offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+            let fetched_at = fetched_at
+                .to_offset(offset)
+                .format(format_description!("[hour]:[minute]"))
+                .unwrap_or_default();
+            rsx! {
+                div { class: "flex w-full px-2",
+                    div { class: "bg-gray-200 border border-gray-400 text-gray-700 px-4 py-2 rounded mb-4 w-full text-sm",
+                        "Offline, showing data from {fetched_at}"
+                    }
                 }
             }
-            div { class: "w-full sm:w-1/2",
-                div { class: "flex mb-2 justify-between items-center",
-                    span { "Temp min" }
-                    small { class: "px-2 inline-block", "{min:0.2}°F" }
+        });
+        let alerts = cache.get(location).map_or_else(Vec::new, |e| {
+            active_alerts(&e.alerts, dismissed_alerts, OffsetDateTime::now_utc())
+        });
+        let alerts_offset = cache
+            .get(location)
+            .and_then(|e| e.weather.as_ref())
+            .map_or(UtcOffset::UTC, |w| w.timezone.into());
+        let alerts_element = alerts.into_iter().map(move |alert| {
+            let id = alert.id();
+            let key = id.clone();
+            let dismiss = rsx! {
+                button { class: "ml-4 px-2 py-1 rounded bg-gray-700 text-white",
+                    onclick: move |_| {
+                        set_dismissed_alerts.modify(|d| {
+                            let mut d = d.clone();
+                            d.push(id.clone());
+                            d
+                        });
+                        set_dismissed_alerts.needs_update();
+                    },
+                    "Dismiss"
                 }
+            };
+            let banner = alert_banner(alert, alerts_offset, dismiss);
+            rsx! {
+                div { key: "alert-key-{key}", banner }
             }
-            div { class: "w-full sm:w-1/2",
-                div { class: "flex mb-2 justify-between items-center",
-                    span { "Temp max" }
-                    small { class: "px-2 inline-block", "{max:0.2}°F" }
+        });
+        let request_state = RequestState::from_entry(cache.get(location), pending);
+        let loading = request_state == RequestState::Loading;
+        debug!("request_state {location:?} {request_state:?}");
+        let view_tabs = ForecastView::ALL.iter().map(|view| {
+            let view = *view;
+            let label = view.to_str();
+            let class = if view == *forecast_view {
+                "px-3 py-1 mx-1 rounded bg-gray-700 text-white"
+            } else {
+                "px-3 py-1 mx-1 rounded text-gray-300"
+            };
+            rsx! {
+                button {
+                    key: "forecast-view-{label}",
+                    class: "{class}",
+                    onclick: move |_| set_forecast_view.set(view),
+                    "{label}"
                 }
             }
-        }
-    )
-}
-
-fn country_info<'a>(weather: &'a WeatherData) -> LazyNodes<'a, 'a> {
-    let name = &weather.name;
-    let country = weather.sys.country.as_ref().map_or("", String::as_str);
-    let mut main = String::new();
-    let mut desc = String::new();
-    let mut icon = String::new();
-    if let Some(weather) = weather.weather.get(0) {
-        main.push_str(&weather.main);
-        desc.push_str(&weather.description);
-        icon.push_str(&weather.icon);
-    }
-    let temp = weather.main.temp.fahrenheit();
-    let fo: UtcOffset = weather.timezone.into();
-    let date = weather.dt.to_offset(fo);
-
-    rsx!(
-        div { class: "flex mb-4 justify-between items-center",
-            div {
-                h5 { class: "mb-0 font-medium text-xl",
-                    "{name} {country}"
-                }
-                small {
-                    img { class: "block w-8 h-8",
-                        src: "https://openweathermap.org/img/wn/{icon}@2x.png",
+        }).collect();
+        let card_element = weather_card(cache.get(location), *units, *forecast_view, loading, view_tabs);
+
+        let version = env!("CARGO_PKG_VERSION");
+        let api_url = &API.url;
+        let api_source = API.source;
+
+        let parse_hint = match parse_location(draft) {
+            Err(ParseError::Empty) | Ok(_) => None,
+            Err(e) => Some(rsx! {
+                span { class: "text-xs text-yellow-700 mt-1", "{e}, searching by city name" }
+            }),
+        };
+
+        let favourites_element = (!favourites.is_empty()).then(|| rsx! {
+            div { class: "flex flex-wrap w-full px-1 mb-2",
+                favourites.iter().map(|s| {
+                    let fav_location = get_parameters(s);
+                    let cached = cache.get(&fav_location);
+                    let class = if fav_location == *location {
+                        "bg-gray-900 text-white rounded-lg shadow-sm px-3 pt-3 cursor-pointer ring-2 ring-yellow-600"
+                    } else {
+                        "bg-gray-900 text-white rounded-lg shadow-sm px-3 pt-3 cursor-pointer"
+                    };
+                    let info_element = match cached.and_then(|e| e.weather.as_ref()) {
+                        Some(weather) => country_info(weather, *units),
+                        None => rsx! { div { class: "mb-4", "{s}" } },
+                    };
+                    let unpin = s.clone();
+                    rsx! {
+                        div { class: "w-1/2 p-1",
+                            key: "favourite-key-{s}",
+                            div { class: "{class}",
+                                onclick: move |_| {
+                                    set_location.modify(|_| fav_location.clone());
+                                    set_location.needs_update();
+                                },
+                                info_element,
+                            }
+                            button { class: "text-xs text-gray-500 hover:text-gray-900",
+                                onclick: move |_| {
+                                    set_favourites.modify(|f| f.iter().filter(|x| **x != unpin).cloned().collect());
+                                    set_favourites.needs_update();
+                                },
+                                "Unpin"
+                            }
+                        }
                     }
-                }
+                })
             }
-            div { class: "text-right",
-                h6 { class: "mb-0",
-                    "{date}"
+        });
+
+        let compare_element = comparing.then(|| {
+            // A compared location stays out of the cache until its fetch
+            // comes back, so a missing entry is still loading
+            let compared_entries: Vec<_> = compared
+                .iter()
+                .map(|s| {
+                    let entry = cache.get(&get_parameters(s));
+                    let label = search_history
+                        .iter()
+                        .find(|h| h.search == *s)
+                        .map_or_else(|| s.clone(), |h| h.label.clone());
+                    (label, entry, RequestState::from_entry(entry, entry.is_none()))
+                })
+                .collect();
+            let statuses: Vec<_> = compared_entries
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, (label, entry, state))| match (state, entry.and_then(|e| e.error.as_ref())) {
+                    (RequestState::Loading, _) => Some((idx, false, format_sstr!("Loading {label}..."))),
+                    (RequestState::Failed, Some(e)) => Some((idx, true, format_sstr!("{label}: {e}"))),
+                    _ => None,
+                })
+                .collect();
+            let status_element = statuses.into_iter().map(|(idx, failed, message)| {
+                let class = if failed {
+                    "px-6 pb-4 text-sm text-red-700"
+                } else {
+                    "px-6 pb-4 text-sm text-gray-300"
+                };
+                rsx! {
+                    div { class: "{class}", key: "compare-status-{idx}", "{message}" }
                 }
-                h3 { class: "font-bold text-4xl mb-0",
-                    span {
-                        "{temp:0.1}°F"
+            });
+            let series: Vec<DailySeries> = compared_entries
+                .iter()
+                .filter_map(|(label, entry, _)| {
+                    let forecast = (*entry)?.forecast.as_ref()?;
+                    Some(DailySeries::new(label.clone(), forecast, *units))
+                })
+                .collect();
+            let chart_element = if series.len() >= 2 {
+                Some(compare_chart(series, *units))
+            } else if compared_entries.len() < 2 {
+                Some(rsx! {
+                    div { class: "px-6 pb-4 text-sm text-gray-300", "Pick two or more locations to compare" }
+                })
+            } else {
+                None
+            };
+            rsx! {
+                div { class: "flex flex-wrap w-full px-2",
+                    div { class: "bg-gray-900 text-white relative min-w-0 break-words rounded-lg overflow-hidden shadow-sm mb-4 w-full",
+                        div { class: "flex flex-wrap px-6 pt-4",
+                            search_history.iter().rev().map(|h| {
+                                let s = h.search.clone();
+                                let key = &h.search;
+                                let label = &h.label;
+                                let checked = compared.contains(&s);
+                                rsx! {
+                                    label { class: "mr-4 mb-2 inline-flex items-center",
+                                        key: "compare-key-{key}",
+                                        input { class: "mr-1",
+                                            "type": "checkbox",
+                                            checked: "{checked}",
+                                            onchange: move |_| {
+                                                set_compared.modify(|c| {
+                                                    if checked {
+                                                        c.iter().filter(|x| **x != s).cloned().collect()
+                                                    } else {
+                                                        let mut c = c.clone();
+                                                        c.push(s.clone());
+                                                        c
+                                                    }
+                                                });
+                                                set_compared.needs_update();
+                                            },
+                                        }
+                                        "{label}"
+                                    }
+                                }
+                            })
+                        }
+                        status_element,
+                        chart_element,
                     }
                 }
             }
-        }
-    )
-}
+        });
+        let compare_label = if *comparing { "Close comparison" } else { "Compare locations" };
 
-fn week_weather<'a>(forecast: &'a WeatherForecast) -> LazyNodes<'a, 'a> {
-    let high_low = forecast.get_high_low();
-    rsx!(
-        div { class: "divider table mx-2 text-center bg-transparent whitespace-nowrap",
-            span { class: "inline-block px-3", small { "Forecast" } }
-        }
-        div { class: "px-6 py-6 relative",
-            div { class: "text-center justify-between items-center flex",
-                style: "flex-flow: initial;",
-                high_low.iter().map(|(d, (h, l, r, s, i))| {
-                    let weekday = d.weekday();
-                    let low = l.fahrenheit();
-                    let high = h.fahrenheit();
-                    let mut rain = String::new();
-                    let mut snow = String::new();
-                    if r.millimeters() > 0.0 {
-                        rain = format!("R {:0.1}\"", r.inches());
-                    }
-                    if s.millimeters() > 0.0 {
-                        snow = format!("S {:0.1}\"", s.inches());
-                    }
-                    let mut icon = String::new();
-                    if let Some(i) = i.iter().next() {
-                        icon.push_str(i);
-                    }
+        let current_search = location_to_search(location);
+        let pinned = favourites.contains(&current_search);
+        let pin_label = if pinned { "Unpin" } else { "Pin" };
 
-                    rsx!(div {
-                            key: "weather-forecast-key-{d}",
-                            class: "text-center mb-0 flex items-center justify-center flex-col",
-                            span { class: "block my-1",
-                                "{weekday}"
+        let suggestions_element = match suggest_future.value() {
+            Some((q, places)) if q == draft && !places.is_empty() => Some(rsx! {
+                ul { class: "absolute z-10 w-full bg-white border border-gray-200 rounded shadow",
+                    places.iter().map(|place| {
+                        let label = place.label();
+                        let place = place.clone();
+                        rsx! {
+                            li { class: "px-2 py-1 cursor-pointer hover:bg-yellow-50 hover:text-gray-900",
+                                key: "suggestion-key-{label}",
+                                onclick: move |_| {
+                                    if let Some(new_location) = place.to_location() {
+                                        let entry = HistoryEntry {
+                                            search: location_to_search(&new_location),
+                                            label: place.label(),
+                                        };
+                                        set_location_cache.modify(|lc| lc.update(entry.search.as_str().into(), new_location.clone()));
+                                        set_location_cache.needs_update();
+                                        set_search_history.modify(|sh| {
+                                            let mut v: Vec<HistoryEntry> = sh.iter().filter(|h| h.search != entry.search).cloned().collect();
+                                            v.push(entry.clone());
+                                            v
+                                        });
+                                        set_search_history.needs_update();
+                                        set_draft.modify(|_| String::new());
+                                        set_draft.needs_update();
+                                        set_location.modify(|_| new_location.clone());
+                                        set_location.needs_update();
+                                    }
+                                },
+                                "{label}"
                             }
-                            img { class: "block w-8 h-8",
-                                src: "https://openweathermap.org/img/wn/{icon}@2x.png",
+                        }
+                    })
+                }
+            }),
+            _ => None,
+        };
+
+        let controls = rsx! {
+            div { class: "inline-flex flex-col justify-center relative text-gray-500",
+                div { class: "relative",
+                    input { class: "p-2 pl-8 rounded border border-gray-200 bg-gray-200 focus:bg-white focus:outline-none focus:ring-2 focus:ring-yellow-600 focus:border-transparent",
+                        placeholder: "search...",
+                        "type": "text",
+                        value: "{draft}",
+                        disabled: "{loading}",
+                        oninput: move |evt| {
+                            let msg = evt.value.as_str();
+                            set_draft.modify(|_| msg.into());
+                            set_draft.needs_update();
+                            let new_location = location_cache.get(msg).map_or_else(
+                                || {
+                                    let l = get_parameters(msg);
+                                    set_location_cache.modify(|lc| lc.update(msg.into(), l.clone()));
+                                    l
+                                }, Clone::clone
+                            );
+                            if cache.contains_key(&new_location) {
+                                debug!("weather_oninput {new_location:?}");
+                                set_location.modify(|_| new_location);
+                                set_location.needs_update();
                             }
-                            span { class: "block my-1",
-                                "{low:0.1}F°"
+                        },
+                        onkeydown: move |evt| {
+                            let new_location = location_cache.get(draft).map_or_else(
+                                || {
+                                    let l = get_parameters(draft);
+                                    set_location_cache.modify(|lc| lc.update(draft.into(), l.clone()));
+                                    l
+                                }, Clone::clone
+                            );
+                            if evt.key == "Enter" {
+                                set_draft.modify(|_| String::new());
+                                set_draft.needs_update();
+                                set_search_history.modify(|sh| {
+                                    let mut v: Vec<HistoryEntry> = sh.iter().filter(|h| h.search != draft.as_str()).cloned().collect();
+                                    v.push(HistoryEntry::new(draft.as_str()));
+                                    v
+                                });
+                                set_location.modify(|_| new_location);
+                                set_location.needs_update();
                             }
-                            span { class: "block my-1",
-                                "{high:0.1}F°"
+                        },
+                    }
+                    svg { class: "w-4 h-4 absolute left-2.5 top-3.5",
+                        "viewBox": "0 0 24 24",
+                        fill: "none",
+                        stroke: "currentColor",
+                        xmlns: "https://www.w3.org/2000/svg",
+                        path {
+                            d: "M21 21l-6-6m2-5a7 7 0 11-14 0 7 7 0 0114 0z",
+                            "stroke-linejoin": "round",
+                            "stroke-linecap": "round",
+                            "stroke-width": "2",
+                        }
+                    }
+                    suggestions_element,
+                }
+                parse_hint,
+            }
+            select { class: "bg-white border border-gray-100 w-full mt-2",
+                id: "history-selector",
+                onchange: move |x| {
+                    let s = x.data.value.as_str();
+                    let new_location = location_cache.get(s).map_or_else(|| {
+                        let l = get_parameters(s);
+                        set_location_cache.modify(|lc| lc.update(s.into(), l.clone()));
+                        set_location_cache.needs_update();
+                        set_search_history.modify(|sh| {
+                            let entry = sh.iter().find(|h| h.search == s).cloned().unwrap_or_else(|| HistoryEntry::new(s));
+                            let mut v: Vec<HistoryEntry> = sh.iter().filter(|h| h.search != s).cloned().collect();
+                            v.push(entry);
+                            v
+                        });
+                        set_search_history.needs_update();
+                        l
+                    }, Clone::clone);
+                    debug!("{new_location:?}");
+                    set_location.modify(|_| new_location);
+                    set_location.needs_update();
+                },
+                {
+                    search_history.iter().rev().map(|h| {
+                        let s = &h.search;
+                        let label = &h.label;
+                        let selected = location_cache.contains_key(s.as_str());
+                        rsx! {
+                            option { class: "pl-8 pr-2 py-1 border-b-2 border-gray-100 relative cursor-pointer hover:bg-yellow-50 hover:text-gray-900",
+                                key: "search-history-key-{s}",
+                                value: "{s}",
+                                selected: "{selected}",
+                                "{label}"
                             }
-                            span { class: "block my-1",
-                                "{rain}"
+                        }
+                    })
+                }
+            }
+            button { class: "bg-white border border-gray-100 w-full mt-2 py-1 hover:bg-yellow-50",
+                id: "favourite-toggle",
+                onclick: move |_| {
+                    set_favourites.modify(|f| {
+                        if pinned {
+                            f.iter().filter(|x| **x != current_search).cloned().collect()
+                        } else {
+                            let mut f = f.clone();
+                            f.push(current_search.clone());
+                            f
+                        }
+                    });
+                    set_favourites.needs_update();
+                },
+                "{pin_label}"
+            }
+            button { class: "bg-white border border-gray-100 w-full mt-2 py-1 hover:bg-yellow-50",
+                id: "locate-button",
+                onclick: move |_| set_locate_requests.set(locate_requests + 1),
+                "Use my location"
+            }
+            button { class: "bg-white border border-gray-100 w-full mt-2 py-1 hover:bg-yellow-50",
+                id: "compare-toggle",
+                onclick: move |_| set_comparing.set(!*comparing),
+                "{compare_label}"
+            }
+            div { class: "flex w-full mt-2",
+                select { class: "bg-white border border-gray-100 w-full",
+                    id: "temperature-unit-selector",
+                    onchange: move |x| {
+                        if let Ok(temperature) = x.data.value.parse() {
+                            let new_units = UnitSystem { temperature, ..*units };
+                            set_unit_system(&new_units).unwrap_or_else(|e| debug!("{e:?}"));
+                            set_units.modify(|_| new_units);
+                            set_units.needs_update();
+                        }
+                    },
+                    {
+                        TemperatureUnit::ALL.iter().map(|u| {
+                            let selected = *u == units.temperature;
+                            let value = u.to_str();
+                            let suffix = u.suffix();
+                            rsx! {
+                                option {
+                                    key: "temperature-unit-key-{value}",
+                                    value: "{value}",
+                                    selected: "{selected}",
+                                    "{suffix}"
+                                }
                             }
-                            span { class: "block my-1",
-                                "{snow}"
+                        })
+                    }
+                }
+                select { class: "bg-white border border-gray-100 w-full",
+                    id: "precipitation-unit-selector",
+                    onchange: move |x| {
+                        if let Ok(precipitation) = x.data.value.parse() {
+                            let new_units = UnitSystem { precipitation, ..*units };
+                            set_unit_system(&new_units).unwrap_or_else(|e| debug!("{e:?}"));
+                            set_units.modify(|_| new_units);
+                            set_units.needs_update();
+                        }
+                    },
+                    {
+                        PrecipitationUnit::ALL.iter().map(|u| {
+                            let selected = *u == units.precipitation;
+                            let value = u.to_str();
+                            let suffix = u.suffix();
+                            rsx! {
+                                option {
+                                    key: "precipitation-unit-key-{value}",
+                                    value: "{value}",
+                                    selected: "{selected}",
+                                    "{suffix}"
+                                }
                             }
+                        })
+                    }
+                }
+                select { class: "bg-white border border-gray-100 w-full",
+                    id: "speed-unit-selector",
+                    onchange: move |x| {
+                        if let Ok(speed) = x.data.value.parse() {
+                            let new_units = UnitSystem { speed, ..*units };
+                            set_unit_system(&new_units).unwrap_or_else(|e| debug!("{e:?}"));
+                            set_units.modify(|_| new_units);
+                            set_units.needs_update();
                         }
-                    )
-                })
-            }
-        }
-    )
-}
-
-fn get_parameters(search_str: &str) -> WeatherLocation {
-    let mut opts = WeatherLocation::from_city_name(search_str);
-    if let Ok(zip) = search_str.parse::<u64>() {
-        opts = WeatherLocation::from_zipcode(zip);
-    } else if search_str.contains(',') {
-        let mut iter = search_str.split(',');
-        if let Some(lat) = iter.next() {
-            if let Ok(lat) = lat.parse() {
-                if let Some(lon) = iter.next() {
-                    if let Ok(lon) = lon.parse() {
-                        opts = WeatherLocation::from_lat_lon(lat, lon);
+                    },
+                    {
+                        SpeedUnit::ALL.iter().map(|u| {
+                            let selected = *u == units.speed;
+                            let value = u.to_str();
+                            let suffix = u.suffix();
+                            rsx! {
+                                option {
+                                    key: "speed-unit-key-{value}",
+                                    value: "{value}",
+                                    selected: "{selected}",
+                                    "{suffix}"
+                                }
+                            }
+                        })
                     }
                 }
             }
-        }
-    }
-    opts
+        };
+        let notices = [location_error_element, offline_element, error_element, favourites_element]
+            .into_iter()
+            .flatten()
+            .chain(alerts_element)
+            .collect();
+        let about_element = rsx! {
+            details { class: "text-xs text-gray-500 w-full px-2",
+                summary { class: "cursor-pointer", "About" }
+                p { "weatherapp {version}" }
+                p { "API endpoint: {api_url} ({api_source})" }
+            }
+        };
+        let below = compare_element.into_iter().chain(Some(about_element)).collect();
+        page_layout(controls, notices, card_element, below)
+    })
 }
 
 async fn get_weather_data_forecast(location: &WeatherLocation) -> WeatherEntry {
     debug!("{location:?}");
-    let weather = get_weather_data(location).await.ok();
-    let forecast = get_weather_forecast(location).await.ok();
-    WeatherEntry { weather, forecast }
+    let provider = FetchProvider::new(&API.url);
+    let entry = fetch_entry(&provider, location)
+        .await
+        .with_cached_at(provider.cached_at.get());
+    if let Some(e) = &entry.error {
+        error!("{location:?} {e}");
+    }
+    entry
 }
 
-async fn get_weather_data(loc: &WeatherLocation) -> Result<WeatherData, Error> {
-    let options = loc.get_options();
-    run_api("weather", &options).await
+/// Locations for `searches` that have no fresh, successful entry in `cache`
+fn uncached_locations(
+    cache: &HashMap<WeatherLocation, WeatherEntry>,
+    searches: &[StackString],
+) -> Vec<WeatherLocation> {
+    searches
+        .iter()
+        .map(|s| get_parameters(s))
+        .filter(|l| {
+            !matches!(
+                cache.get(l),
+                Some(e) if e.error.is_none() && !e.is_stale(get_cache_ttl())
+            )
+        })
+        .collect()
 }
 
-async fn get_weather_forecast(loc: &WeatherLocation) -> Result<WeatherForecast, Error> {
-    let options = loc.get_options();
-    run_api("forecast", &options).await
+fn get_cache_ttl() -> Duration {
+    *CACHE_TTL
 }
 
-async fn run_api<T: serde::de::DeserializeOwned>(
-    command: &str,
-    options: &[(&'static str, String)],
-) -> Result<T, Error> {
-    let base_url = format!("{API_ENDPOINT}{command}");
-    let url = Url::parse_with_params(&base_url, options)?;
-    let json = js_fetch(&url, Method::GET)
-        .await
-        .map_err(|e| format_err!("{:?}", e))?;
-    serde_wasm_bindgen::from_value(json).map_err(|e| format_err!("{:?}", e))
+/// Keeping the weather cache in localStorage is opt in, since a few
+/// locations' forecasts take up a good part of the quota.
+fn persist_cache() -> bool {
+    option_env!("WEATHER_PERSIST_CACHE").map_or(false, |s| s == "1" || s == "true")
+}
+
+fn get_refresh_interval_seconds() -> u32 {
+    *REFRESH_INTERVAL_SECONDS
+}
+
+/// Fetches from the weather proxy, which mirrors the openweathermap api.
+struct FetchProvider {
+    endpoint: StackString,
+    /// Oldest response the service worker answered from its cache
+    cached_at: Cell<Option<OffsetDateTime>>,
+}
+
+impl FetchProvider {
+    fn new(endpoint: &str) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            cached_at: Cell::new(None),
+        }
+    }
+
+    async fn run_api<T: serde::de::DeserializeOwned>(
+        &self,
+        command: &str,
+        options: &[(&'static str, ApiStringType)],
+    ) -> Result<T, WeatherError> {
+        let base_url = format!("{}{command}", self.endpoint);
+        let url = Url::parse_with_params(&base_url, options)
+            .map_err(|e| WeatherError::Network(format_sstr!("{e}")))?;
+        let resp = js_fetch_response(&url, Method::GET).await?;
+        if let Some(cached_at) = get_cached_at(&resp) {
+            let oldest = self.cached_at.get().map_or(cached_at, |t| t.min(cached_at));
+            self.cached_at.set(Some(oldest));
+        }
+        let json = response_json(&resp).await?;
+        serde_wasm_bindgen::from_value(json).map_err(WeatherError::json_decode)
+    }
+}
+
+#[async_trait(?Send)]
+impl WeatherProvider for FetchProvider {
+    async fn weather(&self, location: &WeatherLocation) -> Result<CurrentWeather, WeatherError> {
+        let options = location.get_options();
+        self.run_api("weather", &options).await
+    }
+
+    async fn forecast(&self, location: &WeatherLocation) -> Result<HourlyForecast, WeatherError> {
+        let options = location.get_options();
+        self.run_api("forecast", &options).await
+    }
+
+    async fn geocode(&self, query: &str) -> Result<Vec<GeoLocation>, WeatherError> {
+        let options = [("q", query.into()), ("limit", "5".into())];
+        self.run_api(&GEOCODE_PATH, &options).await
+    }
+
+    async fn alerts(&self, location: &WeatherLocation) -> Result<Vec<WeatherAlert>, WeatherError> {
+        let mut options = location.get_options();
+        options.push(("exclude", "current,minutely,hourly,daily".into()));
+        let one_call: OneCallAlerts = self.run_api("onecall", &options).await?;
+        Ok(one_call.alerts)
+    }
 }
 
-async fn js_fetch(url: &Url, method: Method) -> Result<JsValue, JsValue> {
-    let mut opts = RequestInit::new();
-    opts.method(method.as_str());
+async fn js_fetch(url: &Url, method: Method) -> Result<JsValue, WeatherError> {
+    let resp = js_fetch_response(url, method).await?;
+    response_json(&resp).await
+}
+
+async fn js_fetch_response(url: &Url, method: Method) -> Result<Response, WeatherError> {
+    let opts = RequestInit::new();
+    opts.set_method(method.as_str());
 
-    let request = Request::new_with_str_and_init(url.as_str(), &opts)?;
-    let window = web_sys::window().unwrap();
-    let resp = JsFuture::from(window.fetch_with_request(&request)).await?;
+    let request = Request::new_with_str_and_init(url.as_str(), &opts)
+        .map_err(WeatherError::network)?;
+    let window = window().unwrap();
+    let resp = JsFuture::from(window.fetch_with_request(&request))
+        .await
+        .map_err(WeatherError::network)?;
     let resp: Response = resp.dyn_into().unwrap();
-    JsFuture::from(resp.json()?).await
-}
-
-fn update_location(send: Sender<Location>) -> Result<(), JsValue> {
-    let window = web_sys::window().unwrap();
-    let navigator = window.navigator();
-    let geolocation = navigator.geolocation()?;
-    debug!("geolocation {:?}", geolocation);
-    let closure = Closure::once(move |js: JsValue| {
-        debug!("js {:?}", js);
-        if let Ok(location) = serde_wasm_bindgen::from_value::<Location>(js) {
-            debug!("location {:?}", location);
-            send.send(location).unwrap();
+    if !resp.ok() {
+        return Err(WeatherError::HttpStatus(resp.status()));
+    }
+    Ok(resp)
+}
+
+async fn response_json(resp: &Response) -> Result<JsValue, WeatherError> {
+    let json = resp.json().map_err(WeatherError::json_decode)?;
+    JsFuture::from(json).await.map_err(WeatherError::json_decode)
+}
+
+/// The service worker stamps responses it answers from its cache (see sw.js)
+/// with the time they were originally fetched, in milliseconds.
+fn get_cached_at(resp: &Response) -> Option<OffsetDateTime> {
+    let millis: i128 = resp.headers().get(CACHED_AT_HEADER).ok()??.parse().ok()?;
+    OffsetDateTime::from_unix_timestamp_nanos(millis * 1_000_000).ok()
+}
+
+type LocationSender = Rc<RefCell<Option<Sender<Result<Location, GeolocationError>>>>>;
+
+/// Only the first of the success, error and timer callbacks is passed on.
+fn resolve_location(send: &LocationSender, result: Result<Location, GeolocationError>) {
+    if let Some(send) = send.borrow_mut().take() {
+        if send.send(result).is_err() {
+            debug!("location receiver dropped");
         }
-    });
-    if let Some(closure) = closure.as_ref().dyn_ref() {
-        geolocation.get_current_position(closure)?;
-        debug!("success");
+    }
+}
+
+/// The `timeout` in `PositionOptions` doesn't start until the permission
+/// prompt is answered, so a separate timer covers a prompt that is ignored.
+fn update_location(send: Sender<Result<Location, GeolocationError>>) -> Result<(), JsValue> {
+    let window = window().ok_or_else(|| JsValue::from_str("No window"))?;
+    let geolocation = window.navigator().geolocation()?;
+    debug!("geolocation {geolocation:?}");
+    let send: LocationSender = Rc::new(RefCell::new(Some(send)));
+    let success = {
+        let send = send.clone();
+        Closure::once_into_js(move |js: JsValue| {
+            debug!("js {js:?}");
+            let location = js_sys::Reflect::get(&js, &"coords".into())
+                .ok()
+                .and_then(|coords| serde_wasm_bindgen::from_value::<Location>(coords).ok())
+                .ok_or(GeolocationError::PositionUnavailable);
+            debug!("location {location:?}");
+            resolve_location(&send, location);
+        })
+    };
+    let failure = {
+        let send = send.clone();
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        Closure::once_into_js(move |js: JsValue| {
+            debug!("js {js:?}");
+            let code = js_sys::Reflect::get(&js, &"code".into())
+                .ok()
+                .and_then(|code| code.as_f64())
+                .map_or(0, |code| code as u16);
+            resolve_location(&send, Err(GeolocationError::from_code(code)));
+        })
+    };
+    let options = PositionOptions::new();
+    options.set_enable_high_accuracy(true);
+    options.set_maximum_age(GEOLOCATION_MAX_AGE_MS);
+    options.set_timeout(GEOLOCATION_TIMEOUT_MS);
+    geolocation.get_current_position_with_error_callback_and_options(
+        success.unchecked_ref(),
+        Some(failure.unchecked_ref()),
+        &options,
+    )?;
+    Timeout::new(GEOLOCATION_PROMPT_TIMEOUT_MS, move || {
+        resolve_location(&send, Err(GeolocationError::Timeout));
+    })
+    .forget();
+    Ok(())
+}
+
+/// ipwhois looks up the caller's address when none is given.
+async fn get_location_from_ip() -> Result<Location, WeatherError> {
+    let url = Url::parse(IP_LOCATION_URL).map_err(|e| WeatherError::Network(format_sstr!("{e}")))?;
+    let json = js_fetch(&url, Method::GET).await?;
+    let location: Location = serde_wasm_bindgen::from_value(json).map_err(WeatherError::json_decode)?;
+    debug!("ip location {location:?}");
+    Ok(location)
+}
+
+fn get_route() -> AppRoute {
+    window()
+        .and_then(|w| w.location().search().ok())
+        .map(|search| AppRoute::from_query(&search))
+        .unwrap_or_default()
+}
+
+/// Adds a history entry when the route changes, except that the entry the app
+/// was opened with is replaced if its url had no location yet, so back leaves
+/// the app instead of showing the same view again.
+fn push_route(route: &AppRoute) -> Result<(), JsValue> {
+    let window = window().ok_or_else(|| JsValue::from_str("No window"))?;
+    let location = window.location();
+    let search = location.search()?;
+    let query = route.to_query(&search);
+    if query.as_str() == search {
+        return Ok(());
+    }
+    let url = format!("{}{query}{}", location.pathname()?, location.hash()?);
+    let history = window.history()?;
+    if AppRoute::from_query(&search).location.is_none() {
+        history.replace_state_with_url(&JsValue::NULL, "", Some(&url))
     } else {
-        debug!("failure");
+        history.push_state_with_url(&JsValue::NULL, "", Some(&url))
     }
+}
+
+/// Back and forward restore the location, units and forecast view from the
+/// url; the units fall back to the stored ones when the url has none.
+fn listen_popstate(
+    set_location: Rc<dyn Fn(WeatherLocation)>,
+    set_units: Rc<dyn Fn(UnitSystem)>,
+    set_forecast_view: Rc<dyn Fn(ForecastView)>,
+) -> Result<(), JsValue> {
+    let window = window().ok_or_else(|| JsValue::from_str("No window"))?;
+    let callback = Closure::<dyn FnMut()>::new(move || {
+        let route = get_route();
+        debug!("popstate {route:?}");
+        if let Some(location) = route.get_location() {
+            set_location(location);
+        }
+        set_units(route.get_units(get_unit_system().unwrap_or_default()));
+        set_forecast_view(route.view.unwrap_or_default());
+    });
+    window.add_event_listener_with_callback("popstate", callback.as_ref().unchecked_ref())?;
+    // The app lives as long as the page, so the listener is never removed
+    callback.forget();
+    Ok(())
+}
+
+fn set_unit_system(units: &UnitSystem) -> Result<(), JsValue> {
+    let window = window().ok_or_else(|| JsValue::from_str("No window"))?;
+    let local_storage = window
+        .local_storage()?
+        .ok_or_else(|| JsValue::from_str("No local storage"))?;
+    let units_str = serde_json::to_string(units).map_err(|e| {
+        let e: JsValue = format!("{e}").into();
+        e
+    })?;
+    local_storage.set_item("units", &units_str)?;
+    Ok(())
+}
+
+fn get_unit_system() -> Result<UnitSystem, JsValue> {
+    let window = window().ok_or_else(|| JsValue::from_str("No window"))?;
+    let local_storage = window
+        .local_storage()?
+        .ok_or_else(|| JsValue::from_str("No local storage"))?;
+    match local_storage.get_item("units")? {
+        Some(s) => serde_json::from_str(&s).map_err(|e| {
+            let e: JsValue = format!("{e}").into();
+            e
+        }),
+        None => Ok(UnitSystem::default()),
+    }
+}
+
+fn get_query_param(name: &str) -> Option<String> {
+    let search = window()?.location().search().ok()?;
+    form_urlencoded::parse(search.trim_start_matches('?').as_bytes())
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.into_owned())
+}
+
+fn get_meta_content(name: &str) -> Option<String> {
+    window()?
+        .document()?
+        .query_selector(&format!("meta[name={name}]"))
+        .ok()
+        .flatten()?
+        .get_attribute("content")
+}
+
+fn get_web_config() -> WebConfig {
+    window()
+        .as_ref()
+        .and_then(Window::document)
+        .and_then(|d| d.get_element_by_id("weather-config"))
+        .and_then(|e| e.text_content())
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// The endpoint can be set with `?api_endpoint=...`, a
+/// `<meta name="weather-api-endpoint">` tag or a `weather-config` json script
+/// in index.html, or `WEATHER_API_ENDPOINT` at build time.
+fn resolve_api_endpoint() -> ApiEndpoint {
+    let query = get_query_param("api_endpoint");
+    let meta = get_meta_content("weather-api-endpoint");
+    let json = get_web_config().api_endpoint;
+    let endpoint = ApiEndpoint::resolve(
+        [
+            (EndpointSource::QueryParam, query.as_deref()),
+            (EndpointSource::MetaTag, meta.as_deref()),
+            (EndpointSource::JsonConfig, json.as_deref()),
+            (EndpointSource::BuildEnv, option_env!("WEATHER_API_ENDPOINT")),
+        ],
+        API_ENDPOINT,
+    );
+    debug!("api endpoint {endpoint:?}");
+    endpoint
+}
+
+/// The geocoding path under the endpoint is set the same way as the endpoint,
+/// with `?geocode_path=...`, `<meta name="weather-geocode-path">`,
+/// `geocode_path` in the `weather-config` json or `WEATHER_GEOCODE_PATH`.
+fn resolve_web_geocode_path() -> StackString {
+    let query = get_query_param("geocode_path");
+    let meta = get_meta_content("weather-geocode-path");
+    let json = get_web_config().geocode_path;
+    let path = resolve_geocode_path([
+        query.as_deref(),
+        meta.as_deref(),
+        json.as_deref(),
+        option_env!("WEATHER_GEOCODE_PATH"),
+    ]);
+    debug!("geocode path {path}");
+    path
+}
+
+/// Durations are set like the endpoint, with `?{name}=...`, a
+/// `<meta name="weather-{name}">` tag (with dashes for underscores), `name` in
+/// the `weather-config` json or `build_env` at build time.
+fn resolve_web_seconds(name: &str, json: Option<u32>, build_env: Option<&str>, default: u32) -> u32 {
+    let query = get_query_param(name);
+    let meta = get_meta_content(&format!("weather-{}", name.replace('_', "-")));
+    let json = json.map(|n| n.to_string());
+    let seconds = resolve_seconds(
+        [query.as_deref(), meta.as_deref(), json.as_deref(), build_env],
+        default,
+    );
+    debug!("{name} {seconds}");
+    seconds
+}
+
+fn set_stored_state(state: &StoredState) -> Result<(), JsValue> {
+    let window = window().ok_or_else(|| JsValue::from_str("No window"))?;
+    let local_storage = window
+        .local_storage()?
+        .ok_or_else(|| JsValue::from_str("No local storage"))?;
+    let state_str = state.to_json().map_err(|e| {
+        let e: JsValue = format!("{e}").into();
+        e
+    })?;
+    local_storage.set_item(STORAGE_KEY, &state_str)?;
     Ok(())
 }
+
+/// Falls back to the history weather-index saved on the same origin, and to an
+/// empty state if the stored data can't be read; unreadable data is kept
+/// under a separate key rather than silently overwritten.
+fn get_stored_state() -> StoredState {
+    let local_storage = match window().map(|w| w.local_storage()) {
+        Some(Ok(Some(local_storage))) => local_storage,
+        _ => return StoredState::default(),
+    };
+    let stored = match local_storage.get_item(STORAGE_KEY) {
+        Ok(Some(s)) => Some(s),
+        _ => local_storage.get_item(LEGACY_HISTORY_KEY).ok().flatten(),
+    };
+    let stored = match stored {
+        Some(stored) => stored,
+        None => return StoredState::default(),
+    };
+    StoredState::from_json(&stored).unwrap_or_else(|e| {
+        error!("Failed to load stored state {e}");
+        let backup_key = format!("{STORAGE_KEY}.corrupt");
+        if let Err(e) = local_storage.set_item(&backup_key, &stored) {
+            error!("Failed to back up stored state {e:?}");
+        }
+        StoredState::default()
+    })
+}
@@ -0,0 +1,125 @@
+// Service worker for the weather app.
+//
+// The app shell (index.html, the wasm bundle, its js glue and the bundled
+// stylesheet) is served from cache and refreshed in the background.
+// `weather`, `forecast`, `onecall` and geocoding (`direct`) api responses go to
+// the network first, and the last successful response is served when the
+// network is down, tagged with an `x-sw-cached-at` header so the app can say
+// how old it is.  Only the newest `MAX_DATA_ENTRIES` responses are kept, and
+// none older than `MAX_DATA_AGE_MS`.
+
+const SHELL_CACHE = "weather-app-shell-v3";
+const DATA_CACHE = "weather-app-data-v2";
+const CACHED_AT_HEADER = "x-sw-cached-at";
+const API_PATH = /\/(weather|forecast|onecall|direct)$/;
+const MAX_DATA_ENTRIES = 100;
+const MAX_DATA_AGE_MS = 7 * 24 * 60 * 60 * 1000;
+const SHELL_FILES = ["./", "./index.html", "./manifest.webmanifest", "./icon.svg"];
+// Icons without an embedded svg, when built with the remote-icons feature
+const SHELL_HOSTS = ["openweathermap.org"];
+
+self.addEventListener("install", (event) => {
+  event.waitUntil(
+    caches.open(SHELL_CACHE).then((cache) => cache.addAll(SHELL_FILES))
+  );
+  self.skipWaiting();
+});
+
+self.addEventListener("activate", (event) => {
+  event.waitUntil(
+    caches
+      .keys()
+      .then((keys) =>
+        Promise.all(
+          keys
+            .filter((key) => key !== SHELL_CACHE && key !== DATA_CACHE)
+            .map((key) => caches.delete(key))
+        )
+      )
+      .then(() => self.clients.claim())
+  );
+});
+
+self.addEventListener("fetch", (event) => {
+  const request = event.request;
+  if (request.method !== "GET") {
+    return;
+  }
+  const url = new URL(request.url);
+  if (API_PATH.test(url.pathname)) {
+    event.respondWith(networkFirst(request));
+  } else if (
+    url.origin === self.location.origin ||
+    SHELL_HOSTS.some((host) => url.hostname.endsWith(host))
+  ) {
+    event.respondWith(staleWhileRevalidate(request));
+  }
+});
+
+async function networkFirst(request) {
+  const cache = await caches.open(DATA_CACHE);
+  try {
+    const response = await fetch(request);
+    if (response.ok) {
+      const headers = new Headers(response.headers);
+      headers.set(CACHED_AT_HEADER, Date.now().toString());
+      const body = await response.clone().blob();
+      await cache.put(
+        request,
+        new Response(body, {
+          status: response.status,
+          statusText: response.statusText,
+          headers,
+        })
+      );
+      await trimDataCache(cache);
+    }
+    return response;
+  } catch (err) {
+    const cached = await cache.match(request);
+    if (cached && !isExpired(cached)) {
+      return cached;
+    }
+    if (cached) {
+      await cache.delete(request);
+    }
+    throw err;
+  }
+}
+
+function isExpired(response) {
+  const cachedAt = Number(response.headers.get(CACHED_AT_HEADER));
+  return !cachedAt || Date.now() - cachedAt > MAX_DATA_AGE_MS;
+}
+
+// Drop expired responses, then the oldest ones past `MAX_DATA_ENTRIES`;
+// `put` moves a request to the end of `keys()`, so the oldest come first.
+async function trimDataCache(cache) {
+  const keys = await cache.keys();
+  const fresh = [];
+  for (const key of keys) {
+    const response = await cache.match(key);
+    if (!response || isExpired(response)) {
+      await cache.delete(key);
+    } else {
+      fresh.push(key);
+    }
+  }
+  for (const key of fresh.slice(0, Math.max(0, fresh.length - MAX_DATA_ENTRIES))) {
+    await cache.delete(key);
+  }
+}
+
+async function staleWhileRevalidate(request) {
+  const cache = await caches.open(SHELL_CACHE);
+  const cached = await cache.match(request);
+  const network = fetch(request)
+    .then((response) => {
+      if (response.ok || response.type === "opaque") {
+        cache.put(request, response.clone());
+      }
+      return response;
+    })
+    .catch(() => cached);
+  return cached || network;
+}
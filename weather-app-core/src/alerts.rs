@@ -0,0 +1,268 @@
+use dioxus::prelude::{dioxus_elements, format_args_f, rsx, LazyNodes, NodeFactory, VNode};
+use serde::{Deserialize, Serialize};
+use stack_string::{format_sstr, StackString};
+use time::{macros::format_description, OffsetDateTime, UtcOffset};
+use weather_util_rust::{weather_api::WeatherLocation, weather_data::WeatherData};
+
+/// Most dismissed alert ids kept between visits
+pub const MAX_DISMISSED_ALERTS: usize = 50;
+
+/// The part of an openweathermap One Call response holding the alerts
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OneCallAlerts {
+    #[serde(default)]
+    pub alerts: Vec<WeatherAlert>,
+}
+
+/// A government weather alert as listed by the One Call api
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WeatherAlert {
+    #[serde(default)]
+    pub sender_name: StackString,
+    pub event: StackString,
+    #[serde(with = "time::serde::timestamp")]
+    pub start: OffsetDateTime,
+    #[serde(with = "time::serde::timestamp")]
+    pub end: OffsetDateTime,
+    #[serde(default)]
+    pub description: StackString,
+    #[serde(default)]
+    pub tags: Vec<StackString>,
+}
+
+/// How urgent an alert is, which picks the colour of its banner
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AlertSeverity {
+    Minor,
+    Moderate,
+    Severe,
+    Extreme,
+}
+
+impl AlertSeverity {
+    #[must_use]
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Minor => "Minor",
+            Self::Moderate => "Moderate",
+            Self::Severe => "Severe",
+            Self::Extreme => "Extreme",
+        }
+    }
+}
+
+impl WeatherAlert {
+    /// The api gives alerts no id, so one is made from the fields that
+    /// identify an alert across refreshes.
+    #[must_use]
+    pub fn id(&self) -> StackString {
+        format_sstr!(
+            "{}:{}:{}",
+            self.sender_name,
+            self.event,
+            self.start.unix_timestamp()
+        )
+    }
+
+    /// The api has no severity either, so it is read from the event name:
+    /// US style emergencies, warnings, watches and advisories, and the red,
+    /// orange and yellow levels of european alerts.
+    #[must_use]
+    pub fn severity(&self) -> AlertSeverity {
+        let event = self.event.to_lowercase();
+        let has = |words: &[&str]| event.split_whitespace().any(|w| words.contains(&w));
+        if has(&["emergency", "extreme", "red"]) {
+            AlertSeverity::Extreme
+        } else if has(&["warning", "orange", "amber"]) {
+            AlertSeverity::Severe
+        } else if has(&["watch", "yellow"]) {
+            AlertSeverity::Moderate
+        } else {
+            AlertSeverity::Minor
+        }
+    }
+
+    #[must_use]
+    pub fn is_active(&self, now: OffsetDateTime) -> bool {
+        self.end > now
+    }
+
+    /// Start and end in the location's local time, e.g. "Mar 4 18:00 - Mar 5 06:00"
+    #[must_use]
+    pub fn time_window(&self, offset: UtcOffset) -> StackString {
+        let format = format_description!("[month repr:short] [day padding:none] [hour]:[minute]");
+        let start = self
+            .start
+            .to_offset(offset)
+            .format(format)
+            .unwrap_or_default();
+        let end = self
+            .end
+            .to_offset(offset)
+            .format(format)
+            .unwrap_or_default();
+        format_sstr!("{start} - {end}")
+    }
+}
+
+/// One Call only takes coordinates, so zipcodes and city names are looked up
+/// at the coordinates the weather api resolved them to.
+#[must_use]
+pub fn alerts_location(location: &WeatherLocation, weather: &WeatherData) -> WeatherLocation {
+    match location {
+        WeatherLocation::LatLon { .. } => location.clone(),
+        _ => WeatherLocation::from_lat_lon(weather.coord.lat, weather.coord.lon),
+    }
+}
+
+/// Alerts that have not ended or been dismissed, most severe first
+#[must_use]
+pub fn active_alerts(
+    alerts: &[WeatherAlert],
+    dismissed: &[StackString],
+    now: OffsetDateTime,
+) -> Vec<WeatherAlert> {
+    let mut active: Vec<_> = alerts
+        .iter()
+        .filter(|a| a.is_active(now) && !dismissed.contains(&a.id()))
+        .cloned()
+        .collect();
+    active.sort_by_key(|a| std::cmp::Reverse(a.severity()));
+    active
+}
+
+/// Banner for one alert, with `dismiss` (the app's dismiss button) on the
+/// right.  `offset` is the location's utc offset.
+#[must_use]
+pub fn alert_banner<'a>(
+    alert: WeatherAlert,
+    offset: UtcOffset,
+    dismiss: LazyNodes<'a, 'a>,
+) -> LazyNodes<'a, 'a> {
+    let severity = alert.severity();
+    let class = match severity {
+        AlertSeverity::Extreme => "bg-red-700 border border-red-900 text-white px-4 py-3 rounded mb-4 w-full flex justify-between items-center",
+        AlertSeverity::Severe => "bg-red-100 border border-red-400 text-red-700 px-4 py-3 rounded mb-4 w-full flex justify-between items-center",
+        AlertSeverity::Moderate => "bg-yellow-100 border border-yellow-400 text-yellow-700 px-4 py-3 rounded mb-4 w-full flex justify-between items-center",
+        AlertSeverity::Minor => "bg-blue-100 border border-blue-400 text-blue-700 px-4 py-3 rounded mb-4 w-full flex justify-between items-center",
+    };
+    let severity = severity.to_str();
+    let window = alert.time_window(offset);
+    let sender = if alert.sender_name.is_empty() {
+        StackString::new()
+    } else {
+        format_sstr!(", {}", alert.sender_name)
+    };
+    let WeatherAlert {
+        event, description, ..
+    } = alert;
+
+    rsx!(
+        div { class: "flex w-full px-2",
+            div { class: "{class}",
+                div { class: "min-w-0",
+                    span { class: "block font-bold",
+                        "{event}"
+                    }
+                    span { class: "block text-xs",
+                        "{severity}, {window}{sender}"
+                    }
+                    p { class: "text-sm mt-1 whitespace-pre-line break-words",
+                        "{description}"
+                    }
+                }
+                dismiss
+            }
+        }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use time::{macros::datetime, Duration, UtcOffset};
+    use weather_util_rust::{weather_api::WeatherLocation, weather_data::WeatherData};
+
+    use crate::{
+        alerts::{active_alerts, alerts_location, AlertSeverity, OneCallAlerts, WeatherAlert},
+        location::get_parameters,
+    };
+
+    fn alerts() -> Vec<WeatherAlert> {
+        let json = r#"{
+            "lat": 40.7, "lon": -73.9, "timezone": "America/New_York",
+            "alerts": [
+                {
+                    "sender_name": "NWS Upton NY",
+                    "event": "Wind Advisory",
+                    "start": 1646431200,
+                    "end": 1646474400,
+                    "description": "West winds 20 to 30 mph.",
+                    "tags": ["Wind"]
+                },
+                {
+                    "sender_name": "NWS Upton NY",
+                    "event": "Flood Warning",
+                    "start": 1646431200,
+                    "end": 1646517600,
+                    "description": "Minor flooding is occurring."
+                }
+            ]
+        }"#;
+        serde_json::from_str::<OneCallAlerts>(json).unwrap().alerts
+    }
+
+    #[test]
+    fn test_one_call_alerts() {
+        let alerts = alerts();
+        assert_eq!(alerts.len(), 2);
+        assert_eq!(alerts[0].start, datetime!(2022-03-04 22:00 UTC));
+        assert_eq!(alerts[0].id(), "NWS Upton NY:Wind Advisory:1646431200");
+        assert!(alerts[1].tags.is_empty());
+        assert_eq!(
+            alerts[0].time_window(UtcOffset::from_hms(-5, 0, 0).unwrap()),
+            "Mar 4 17:00 - Mar 5 05:00"
+        );
+        let none: OneCallAlerts = serde_json::from_str(r#"{"lat": 40.7}"#).unwrap();
+        assert!(none.alerts.is_empty());
+    }
+
+    #[test]
+    fn test_severity() {
+        let mut alert = alerts().remove(0);
+        for (event, severity) in [
+            ("Wind Advisory", AlertSeverity::Minor),
+            ("Special Weather Statement", AlertSeverity::Minor),
+            ("Winter Storm Watch", AlertSeverity::Moderate),
+            ("Yellow Thunderstorm alert", AlertSeverity::Moderate),
+            ("Tornado Warning", AlertSeverity::Severe),
+            ("Extreme Cold Warning", AlertSeverity::Extreme),
+            ("Flash Flood Emergency", AlertSeverity::Extreme),
+        ] {
+            alert.event = event.into();
+            assert_eq!(alert.severity(), severity, "{event}");
+        }
+    }
+
+    #[test]
+    fn test_active_alerts() {
+        let alerts = alerts();
+        let start = alerts[0].start;
+        let active = active_alerts(&alerts, &[], start);
+        assert_eq!(active[0].event, "Flood Warning");
+        assert_eq!(active[1].event, "Wind Advisory");
+        let active = active_alerts(&alerts, &[alerts[1].id()], start);
+        assert_eq!(active, vec![alerts[0].clone()]);
+        let active = active_alerts(&alerts, &[], alerts[0].end + Duration::minutes(1));
+        assert_eq!(active, vec![alerts[1].clone()]);
+    }
+
+    #[test]
+    fn test_alerts_location() {
+        let weather: WeatherData =
+            serde_json::from_str(include_str!("../tests/weather.json")).unwrap();
+        let coords = WeatherLocation::from_lat_lon(weather.coord.lat, weather.coord.lon);
+        assert_eq!(alerts_location(&get_parameters("11106"), &weather), coords);
+        let lat_lon = get_parameters("40.7,-73.9");
+        assert_eq!(alerts_location(&lat_lon, &weather), lat_lon);
+    }
+}
@@ -0,0 +1,3 @@
+/// Tailwind preflight and the utility classes used by the apps, so the
+/// stylesheet is served with the app rather than from a CDN.
+pub static TAILWIND_CSS: &str = include_str!("../assets/tailwind.css");
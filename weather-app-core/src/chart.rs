@@ -0,0 +1,366 @@
+use dioxus::prelude::{dioxus_elements, format_args_f, rsx, LazyNodes, NodeFactory, VNode};
+use stack_string::{format_sstr, StackString};
+use std::fmt::Write;
+use time::{macros::format_description, UtcOffset};
+use weather_util_rust::weather_forecast::WeatherForecast;
+
+use crate::units::UnitSystem;
+
+pub const CHART_WIDTH: f64 = 400.0;
+pub const CHART_HEIGHT: f64 = 120.0;
+pub(crate) static CHART_VIEWBOX: &str = "-36 -8 444 148";
+
+/// Maps forecast slots and values onto svg coordinates, with each slot
+/// centered in an equal share of the width and y growing downwards.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ChartScale {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl ChartScale {
+    /// A flat series is padded by one unit either side so it is drawn across
+    /// the middle of the chart.
+    #[must_use]
+    pub fn new(count: usize, min: f64, max: f64, width: f64, height: f64) -> Self {
+        let (min, max) = if (max - min).abs() < f64::EPSILON {
+            (min - 1.0, max + 1.0)
+        } else {
+            (min, max)
+        };
+        Self {
+            count,
+            min,
+            max,
+            width,
+            height,
+        }
+    }
+
+    /// Scale over the range of `values`, which must not contain `NaN`.
+    #[must_use]
+    pub fn from_values<'a>(values: impl IntoIterator<Item = &'a f64>) -> Self {
+        let (count, min, max) = values.into_iter().fold(
+            (0, f64::INFINITY, f64::NEG_INFINITY),
+            |(count, min, max), v| (count + 1, min.min(*v), max.max(*v)),
+        );
+        if count == 0 {
+            return Self::new(0, 0.0, 1.0, CHART_WIDTH, CHART_HEIGHT);
+        }
+        Self::new(count, min, max, CHART_WIDTH, CHART_HEIGHT)
+    }
+
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn slot_width(&self) -> f64 {
+        self.width / self.count.max(1) as f64
+    }
+
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn x(&self, idx: usize) -> f64 {
+        (idx as f64 + 0.5) * self.slot_width()
+    }
+
+    #[must_use]
+    pub fn y(&self, value: f64) -> f64 {
+        self.height - (value - self.min) / (self.max - self.min) * self.height
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Bar {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// `points` attribute of an svg `polyline` through each value
+#[must_use]
+pub fn line_points(values: &[f64], scale: &ChartScale) -> StackString {
+    let mut points = StackString::new();
+    for (idx, value) in values.iter().enumerate() {
+        if idx > 0 {
+            points.push_str(" ");
+        }
+        write!(points, "{:0.1},{:0.1}", scale.x(idx), scale.y(*value)).unwrap_or(());
+    }
+    points
+}
+
+/// `points` attribute of an svg `polygon` along the highs and back along the
+/// lows, filling the range between them.
+#[must_use]
+pub fn band_points(lows: &[f64], highs: &[f64], scale: &ChartScale) -> StackString {
+    let mut points = line_points(highs, scale);
+    for (idx, value) in lows.iter().enumerate().rev() {
+        if !points.is_empty() {
+            points.push_str(" ");
+        }
+        write!(points, "{:0.1},{:0.1}", scale.x(idx), scale.y(*value)).unwrap_or(());
+    }
+    points
+}
+
+/// Bars rising from the bottom of the chart; the scale should start at zero.
+#[must_use]
+pub fn bars(values: &[f64], scale: &ChartScale) -> Vec<Bar> {
+    let width = scale.slot_width() * 0.8;
+    values
+        .iter()
+        .enumerate()
+        .map(|(idx, value)| {
+            let y = scale.y(*value);
+            Bar {
+                x: scale.x(idx) - width / 2.0,
+                y,
+                width,
+                height: scale.height - y,
+            }
+        })
+        .collect()
+}
+
+/// Slots to label with their day, given the local hour of each slot: each
+/// slot starting a new day, and the first slot only if the partial first day
+/// is long enough that its label doesn't run into the next one.
+#[must_use]
+pub fn day_label_slots(hours: &[u8]) -> Vec<usize> {
+    let day_starts = hours
+        .iter()
+        .enumerate()
+        .filter(|(idx, hour)| *idx > 0 && **hour < 3)
+        .map(|(idx, _)| idx);
+    let first_day = match day_starts.clone().next() {
+        Some(idx) if idx < 3 => None,
+        _ if hours.is_empty() => None,
+        _ => Some(0),
+    };
+    first_day.into_iter().chain(day_starts).collect()
+}
+
+/// Temperature line over a min/max band, and precipitation bars, for each 3
+/// hour slot of the forecast.
+#[must_use]
+pub fn forecast_chart<'a>(forecast: &'a WeatherForecast, units: UnitSystem) -> LazyNodes<'a, 'a> {
+    let fo: UtcOffset = forecast.city.timezone.into();
+    let t_unit = units.temperature.suffix();
+    let p_unit = units.precipitation.suffix();
+
+    let temps: Vec<f64> = forecast
+        .list
+        .iter()
+        .map(|e| units.temperature.convert(e.main.temp))
+        .collect();
+    let lows: Vec<f64> = forecast
+        .list
+        .iter()
+        .map(|e| units.temperature.convert(e.main.temp_min))
+        .collect();
+    let highs: Vec<f64> = forecast
+        .list
+        .iter()
+        .map(|e| units.temperature.convert(e.main.temp_max))
+        .collect();
+    let precip: Vec<f64> = forecast
+        .list
+        .iter()
+        .map(|e| {
+            let rain = e.rain.and_then(|r| r.three_hour).unwrap_or_default();
+            let snow = e.snow.and_then(|s| s.three_hour).unwrap_or_default();
+            units.precipitation.convert(rain + snow)
+        })
+        .collect();
+
+    let t_scale = ChartScale::from_values(lows.iter().chain(highs.iter()).chain(temps.iter()));
+    let temp_line = line_points(&temps, &t_scale);
+    let temp_band = band_points(&lows, &highs, &t_scale);
+    let t_max = format_sstr!("{:0.0}{t_unit}", t_scale.max);
+    let t_min = format_sstr!("{:0.0}{t_unit}", t_scale.min);
+
+    let p_total: f64 = precip.iter().sum();
+    let p_scale = ChartScale::new(
+        precip.len(),
+        0.0,
+        precip.iter().copied().fold(0.0, f64::max),
+        CHART_WIDTH,
+        CHART_HEIGHT,
+    );
+    let p_bars = bars(&precip, &p_scale);
+    let p_max = format_sstr!("{:0.2}{p_unit}", p_scale.max);
+    let p_empty = p_total <= 0.0;
+
+    let hours: Vec<u8> = forecast
+        .list
+        .iter()
+        .map(|e| e.dt.to_offset(fo).hour())
+        .collect();
+    let days: Vec<(f64, StackString)> = day_label_slots(&hours)
+        .into_iter()
+        .map(|idx| {
+            let label = forecast.list[idx]
+                .dt
+                .to_offset(fo)
+                .format(format_description!("[weekday repr:short]"))
+                .unwrap_or_default();
+            (t_scale.x(idx) - t_scale.slot_width() / 2.0, label.into())
+        })
+        .collect();
+    let day_lines = days.clone();
+    let label_y = CHART_HEIGHT + 14.0;
+    let (mid_x, mid_y) = (CHART_WIDTH / 2.0, CHART_HEIGHT / 2.0);
+
+    rsx!(
+        div { class: "divider table mx-2 text-center bg-transparent whitespace-nowrap",
+            span { class: "inline-block px-3", small { "Temperature" } }
+        }
+        div { class: "px-6 py-2 relative",
+            svg { class: "w-full",
+                "viewBox": "{CHART_VIEWBOX}",
+                xmlns: "http://www.w3.org/2000/svg",
+                days.iter().map(|(x, label)| rsx!(
+                    g {
+                        key: "chart-day-{x}",
+                        line { x1: "{x:0.1}", y1: "0", x2: "{x:0.1}", y2: "{CHART_HEIGHT}",
+                            stroke: "currentColor",
+                            "stroke-opacity": "0.2",
+                        }
+                        text { x: "{x:0.1}", y: "{label_y}",
+                            "font-size": "10",
+                            fill: "currentColor",
+                            "{label}"
+                        }
+                    }
+                ))
+                polygon { points: "{temp_band}",
+                    fill: "#fcd34d",
+                    "fill-opacity": "0.3",
+                    stroke: "none",
+                }
+                polyline { points: "{temp_line}",
+                    fill: "none",
+                    stroke: "#f59e0b",
+                    "stroke-width": "2",
+                }
+                text { x: "-4", y: "4", "text-anchor": "end", "font-size": "10", fill: "currentColor",
+                    "{t_max}"
+                }
+                text { x: "-4", y: "{CHART_HEIGHT}", "text-anchor": "end", "font-size": "10", fill: "currentColor",
+                    "{t_min}"
+                }
+            }
+        }
+        div { class: "divider table mx-2 text-center bg-transparent whitespace-nowrap",
+            span { class: "inline-block px-3", small { "Precipitation" } }
+        }
+        div { class: "px-6 py-2 relative",
+            svg { class: "w-full",
+                "viewBox": "{CHART_VIEWBOX}",
+                xmlns: "http://www.w3.org/2000/svg",
+                day_lines.iter().map(|(x, label)| rsx!(
+                    g {
+                        key: "chart-precip-day-{x}",
+                        line { x1: "{x:0.1}", y1: "0", x2: "{x:0.1}", y2: "{CHART_HEIGHT}",
+                            stroke: "currentColor",
+                            "stroke-opacity": "0.2",
+                        }
+                        text { x: "{x:0.1}", y: "{label_y}",
+                            "font-size": "10",
+                            fill: "currentColor",
+                            "{label}"
+                        }
+                    }
+                ))
+                p_bars.iter().enumerate().filter(|(_, b)| b.height > 0.0).map(|(idx, b)| rsx!(
+                    rect {
+                        key: "chart-precip-bar-{idx}",
+                        x: "{b.x:0.1}",
+                        y: "{b.y:0.1}",
+                        width: "{b.width:0.1}",
+                        height: "{b.height:0.1}",
+                        fill: "#60a5fa",
+                    }
+                ))
+                p_empty.then(|| rsx!(
+                    text { x: "{mid_x}", y: "{mid_y}", "text-anchor": "middle", "font-size": "12", fill: "currentColor",
+                        "No precipitation expected"
+                    }
+                ))
+                text { x: "-4", y: "4", "text-anchor": "end", "font-size": "10", fill: "currentColor",
+                    "{p_max}"
+                }
+                text { x: "-4", y: "{CHART_HEIGHT}", "text-anchor": "end", "font-size": "10", fill: "currentColor",
+                    "0"
+                }
+            }
+        }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chart::{band_points, bars, day_label_slots, line_points, Bar, ChartScale};
+
+    #[test]
+    fn test_chart_scale() {
+        let scale = ChartScale::new(4, 0.0, 10.0, 400.0, 100.0);
+        assert!((scale.x(0) - 50.0).abs() < 1e-9);
+        assert!((scale.x(3) - 350.0).abs() < 1e-9);
+        assert!((scale.y(0.0) - 100.0).abs() < 1e-9);
+        assert!((scale.y(10.0)).abs() < 1e-9);
+        assert!((scale.y(5.0) - 50.0).abs() < 1e-9);
+
+        let flat = ChartScale::new(2, 5.0, 5.0, 400.0, 100.0);
+        assert!((flat.y(5.0) - 50.0).abs() < 1e-9);
+
+        let scale = ChartScale::from_values(&[3.0, -1.0, 7.0]);
+        assert_eq!(scale.count, 3);
+        assert!((scale.min + 1.0).abs() < 1e-9);
+        assert!((scale.max - 7.0).abs() < 1e-9);
+        assert_eq!(ChartScale::from_values(&[]).count, 0);
+    }
+
+    #[test]
+    fn test_line_and_band_points() {
+        let scale = ChartScale::new(2, 0.0, 10.0, 200.0, 100.0);
+        assert_eq!(line_points(&[0.0, 10.0], &scale), "50.0,100.0 150.0,0.0");
+        assert_eq!(
+            band_points(&[0.0, 2.0], &[5.0, 10.0], &scale),
+            "50.0,50.0 150.0,0.0 150.0,80.0 50.0,100.0"
+        );
+        assert_eq!(line_points(&[], &scale), "");
+    }
+
+    #[test]
+    fn test_bars() {
+        let scale = ChartScale::new(2, 0.0, 4.0, 200.0, 100.0);
+        let b = bars(&[0.0, 2.0], &scale);
+        assert_eq!(b.len(), 2);
+        assert_eq!(
+            b[1],
+            Bar {
+                x: 110.0,
+                y: 50.0,
+                width: 80.0,
+                height: 50.0
+            }
+        );
+        assert!(b[0].height.abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_day_label_slots() {
+        // Forecast starting at 21:00, with the next day two slots later
+        assert_eq!(
+            day_label_slots(&[21, 0, 3, 6, 9, 12, 15, 18, 21, 0]),
+            vec![1, 9]
+        );
+        assert_eq!(day_label_slots(&[12, 15, 18, 21, 0, 3]), vec![0, 4]);
+        assert_eq!(day_label_slots(&[0, 3, 6]), vec![0]);
+        assert!(day_label_slots(&[]).is_empty());
+    }
+}
@@ -0,0 +1,412 @@
+use dioxus::prelude::{dioxus_elements, format_args_f, rsx, LazyNodes, NodeFactory, VNode};
+use stack_string::{format_sstr, StackString};
+use time::{macros::format_description, Date};
+use weather_util_rust::weather_forecast::WeatherForecast;
+
+use crate::{
+    chart::{line_points, ChartScale, CHART_HEIGHT, CHART_VIEWBOX, CHART_WIDTH},
+    units::UnitSystem,
+};
+
+static SERIES_COLOURS: [&str; 6] = [
+    "#f59e0b", "#3b82f6", "#10b981", "#ef4444", "#8b5cf6", "#ec4899",
+];
+
+/// High, low and total precipitation for one day, in display units
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DaySummary {
+    pub date: Date,
+    pub high: f64,
+    pub low: f64,
+    pub precip: f64,
+}
+
+impl DaySummary {
+    /// How much higher (or lower, if negative) each value is than `base`
+    #[must_use]
+    pub fn diff(&self, base: &Self) -> Self {
+        Self {
+            date: self.date,
+            high: self.high - base.high,
+            low: self.low - base.low,
+            precip: self.precip - base.precip,
+        }
+    }
+}
+
+/// One location's forecast reduced to a `DaySummary` per day
+#[derive(Clone, Debug, PartialEq)]
+pub struct DailySeries {
+    pub label: StackString,
+    pub days: Vec<DaySummary>,
+}
+
+impl DailySeries {
+    #[must_use]
+    pub fn new(
+        label: impl Into<StackString>,
+        forecast: &WeatherForecast,
+        units: UnitSystem,
+    ) -> Self {
+        let days = forecast
+            .get_high_low()
+            .into_iter()
+            .map(|(date, (high, low, rain, snow, _))| DaySummary {
+                date,
+                high: units.temperature.convert(high),
+                low: units.temperature.convert(low),
+                precip: units.precipitation.convert(rain + snow),
+            })
+            .collect();
+        Self {
+            label: label.into(),
+            days,
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, date: Date) -> Option<&DaySummary> {
+        self.days.iter().find(|d| d.date == date)
+    }
+}
+
+/// Dates every series has a forecast for, in order.  Locations in different
+/// timezones can each have a partial day at either end that the others lack.
+#[must_use]
+pub fn common_dates(series: &[DailySeries]) -> Vec<Date> {
+    match series.split_first() {
+        Some((first, rest)) => first
+            .days
+            .iter()
+            .map(|d| d.date)
+            .filter(|date| rest.iter().all(|s| s.get(*date).is_some()))
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+fn signed(value: f64, unit: &str) -> StackString {
+    if value.abs() < 0.05 {
+        format_sstr!("±0{unit}")
+    } else {
+        format_sstr!("{value:+0.1}{unit}")
+    }
+}
+
+/// Daily highs (solid) and lows (dashed) of each series on one chart, grouped
+/// precipitation bars, and a table of each location's difference from the
+/// first one.
+#[must_use]
+pub fn compare_chart<'a>(series: Vec<DailySeries>, units: UnitSystem) -> LazyNodes<'a, 'a> {
+    let t_unit = units.temperature.suffix();
+    let p_unit = units.precipitation.suffix();
+    let dates = common_dates(&series);
+    let summaries: Vec<Vec<DaySummary>> = series
+        .iter()
+        .map(|s| {
+            dates
+                .iter()
+                .filter_map(|date| s.get(*date).copied())
+                .collect()
+        })
+        .collect();
+
+    let t_scale =
+        ChartScale::from_values(summaries.iter().flatten().flat_map(|d| [&d.high, &d.low]));
+    let t_scale = ChartScale {
+        count: dates.len(),
+        ..t_scale
+    };
+    let lines: Vec<(usize, StackString, StackString)> = summaries
+        .iter()
+        .enumerate()
+        .map(|(idx, days)| {
+            let highs: Vec<f64> = days.iter().map(|d| d.high).collect();
+            let lows: Vec<f64> = days.iter().map(|d| d.low).collect();
+            (
+                idx,
+                line_points(&highs, &t_scale),
+                line_points(&lows, &t_scale),
+            )
+        })
+        .collect();
+    let t_max = format_sstr!("{:0.0}{t_unit}", t_scale.max);
+    let t_min = format_sstr!("{:0.0}{t_unit}", t_scale.min);
+
+    let p_scale = ChartScale::new(
+        dates.len(),
+        0.0,
+        summaries
+            .iter()
+            .flatten()
+            .map(|d| d.precip)
+            .fold(0.0, f64::max),
+        CHART_WIDTH,
+        CHART_HEIGHT,
+    );
+    #[allow(clippy::cast_precision_loss)]
+    let bar_width = p_scale.slot_width() * 0.8 / series.len().max(1) as f64;
+    let p_bars: Vec<(StackString, &str, f64, f64, f64)> = summaries
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, days)| {
+            let p_scale = &p_scale;
+            days.iter()
+                .enumerate()
+                .filter(|(_, d)| d.precip > 0.0)
+                .map(move |(slot, d)| {
+                    let y = p_scale.y(d.precip);
+                    #[allow(clippy::cast_precision_loss)]
+                    let x = p_scale.x(slot) - p_scale.slot_width() * 0.4 + bar_width * idx as f64;
+                    (
+                        format_sstr!("{idx}-{slot}"),
+                        SERIES_COLOURS[idx % SERIES_COLOURS.len()],
+                        x,
+                        y,
+                        CHART_HEIGHT - y,
+                    )
+                })
+        })
+        .collect();
+    let p_max = format_sstr!("{:0.2}{p_unit}", p_scale.max);
+
+    let days: Vec<(f64, StackString)> = dates
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, date)| {
+            let label = date
+                .format(format_description!("[weekday repr:short]"))
+                .ok()?;
+            Some((t_scale.x(idx), label.into()))
+        })
+        .collect();
+    let day_labels = days.clone();
+    let label_y = CHART_HEIGHT + 14.0;
+
+    let legend: Vec<(&str, StackString)> = series
+        .into_iter()
+        .enumerate()
+        .map(|(idx, s)| (SERIES_COLOURS[idx % SERIES_COLOURS.len()], s.label))
+        .collect();
+    let base = summaries.first();
+    let rows: Vec<(StackString, Vec<StackString>)> = dates
+        .iter()
+        .enumerate()
+        .map(|(slot, date)| {
+            let weekday = format_sstr!("{}", date.weekday());
+            let cells = summaries
+                .iter()
+                .enumerate()
+                .map(|(idx, days)| match (idx, base.and_then(|b| b.get(slot))) {
+                    (0, _) | (_, None) => {
+                        let d = &days[slot];
+                        format_sstr!(
+                            "{:0.1}{t_unit} / {:0.1}{t_unit}, {:0.1}{p_unit}",
+                            d.high,
+                            d.low,
+                            d.precip
+                        )
+                    }
+                    (_, Some(b)) => {
+                        let d = days[slot].diff(b);
+                        format_sstr!(
+                            "{} / {}, {}",
+                            signed(d.high, t_unit),
+                            signed(d.low, t_unit),
+                            signed(d.precip, p_unit)
+                        )
+                    }
+                })
+                .collect();
+            (weekday, cells)
+        })
+        .collect();
+    let empty = dates.is_empty();
+
+    rsx!(
+        div { class: "flex flex-wrap justify-center px-6 py-2 text-sm",
+            legend.iter().enumerate().map(|(idx, (colour, label))| rsx!(
+                span { class: "inline-flex items-center mx-2",
+                    key: "compare-legend-{idx}",
+                    span { class: "inline-block w-3 h-3 mr-1 rounded-sm",
+                        style: "background-color: {colour}",
+                    }
+                    "{label}"
+                }
+            ))
+        }
+        empty.then(|| rsx!(
+            div { class: "px-6 py-2 text-center", "No days in common to compare" }
+        ))
+        div { class: "divider table mx-2 text-center bg-transparent whitespace-nowrap",
+            span { class: "inline-block px-3", small { "High / Low" } }
+        }
+        div { class: "px-6 py-2 relative",
+            svg { class: "w-full",
+                "viewBox": "{CHART_VIEWBOX}",
+                xmlns: "http://www.w3.org/2000/svg",
+                days.iter().enumerate().map(|(idx, (x, label))| rsx!(
+                    text { x: "{x:0.1}", y: "{label_y}",
+                        key: "compare-day-{idx}",
+                        "text-anchor": "middle",
+                        "font-size": "10",
+                        fill: "currentColor",
+                        "{label}"
+                    }
+                ))
+                lines.iter().map(|(idx, highs, lows)| {
+                    let colour = SERIES_COLOURS[idx % SERIES_COLOURS.len()];
+                    rsx!(
+                        g {
+                            key: "compare-line-{idx}",
+                            polyline { points: "{highs}",
+                                fill: "none",
+                                stroke: "{colour}",
+                                "stroke-width": "2",
+                            }
+                            polyline { points: "{lows}",
+                                fill: "none",
+                                stroke: "{colour}",
+                                "stroke-width": "2",
+                                "stroke-dasharray": "4 3",
+                            }
+                        }
+                    )
+                })
+                text { x: "-4", y: "4", "text-anchor": "end", "font-size": "10", fill: "currentColor",
+                    "{t_max}"
+                }
+                text { x: "-4", y: "{CHART_HEIGHT}", "text-anchor": "end", "font-size": "10", fill: "currentColor",
+                    "{t_min}"
+                }
+            }
+        }
+        div { class: "divider table mx-2 text-center bg-transparent whitespace-nowrap",
+            span { class: "inline-block px-3", small { "Precipitation" } }
+        }
+        div { class: "px-6 py-2 relative",
+            svg { class: "w-full",
+                "viewBox": "{CHART_VIEWBOX}",
+                xmlns: "http://www.w3.org/2000/svg",
+                day_labels.iter().enumerate().map(|(idx, (x, label))| rsx!(
+                    text { x: "{x:0.1}", y: "{label_y}",
+                        key: "compare-precip-day-{idx}",
+                        "text-anchor": "middle",
+                        "font-size": "10",
+                        fill: "currentColor",
+                        "{label}"
+                    }
+                ))
+                p_bars.iter().map(|(key, colour, x, y, height)| rsx!(
+                    rect {
+                        key: "compare-bar-{key}",
+                        x: "{x:0.1}",
+                        y: "{y:0.1}",
+                        width: "{bar_width:0.1}",
+                        height: "{height:0.1}",
+                        fill: "{colour}",
+                    }
+                ))
+                text { x: "-4", y: "4", "text-anchor": "end", "font-size": "10", fill: "currentColor",
+                    "{p_max}"
+                }
+                text { x: "-4", y: "{CHART_HEIGHT}", "text-anchor": "end", "font-size": "10", fill: "currentColor",
+                    "0"
+                }
+            }
+        }
+        div { class: "px-6 py-2 overflow-x-auto",
+            table { class: "w-full text-sm text-left whitespace-nowrap",
+                thead {
+                    tr {
+                        th { class: "pr-4", "Day" }
+                        legend.iter().enumerate().map(|(idx, (_, label))| {
+                            let heading = if idx == 0 {
+                                format_sstr!("{label}")
+                            } else {
+                                format_sstr!("{label} (difference)")
+                            };
+                            rsx!(th { class: "pr-4", key: "compare-heading-{idx}", "{heading}" })
+                        })
+                    }
+                }
+                tbody {
+                    rows.iter().enumerate().map(|(slot, (weekday, cells))| rsx!(
+                        tr {
+                            key: "compare-row-{slot}",
+                            td { class: "pr-4", "{weekday}" }
+                            cells.iter().enumerate().map(|(idx, cell)| rsx!(
+                                td { class: "pr-4", key: "compare-cell-{idx}", "{cell}" }
+                            ))
+                        }
+                    ))
+                }
+            }
+        }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::date;
+    use weather_util_rust::weather_forecast::WeatherForecast;
+
+    use crate::{
+        compare::{common_dates, DailySeries, DaySummary},
+        units::UnitSystem,
+    };
+
+    fn forecast() -> WeatherForecast {
+        serde_json::from_str(include_str!("../tests/forecast.json")).unwrap()
+    }
+
+    #[test]
+    fn test_daily_series() {
+        let series = DailySeries::new("Astoria", &forecast(), UnitSystem::default());
+        assert_eq!(series.label, "Astoria");
+        assert!(!series.days.is_empty());
+        assert!(series.days.windows(2).all(|w| w[0].date < w[1].date));
+        assert!(series
+            .days
+            .iter()
+            .all(|d| d.high >= d.low && d.precip >= 0.0));
+        let first = series.days[0];
+        assert_eq!(series.get(first.date), Some(&first));
+    }
+
+    #[test]
+    fn test_common_dates() {
+        let series = DailySeries::new("Astoria", &forecast(), UnitSystem::default());
+        let mut shorter = series.clone();
+        shorter.days.remove(0);
+        let dates = common_dates(&[series.clone(), shorter.clone()]);
+        assert_eq!(dates.len(), series.days.len() - 1);
+        assert_eq!(dates[0], shorter.days[0].date);
+        assert_eq!(
+            common_dates(std::slice::from_ref(&series)).len(),
+            series.days.len()
+        );
+        assert!(common_dates(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_diff() {
+        let base = DaySummary {
+            date: date!(2022 - 02 - 28),
+            high: 10.0,
+            low: 2.0,
+            precip: 1.5,
+        };
+        let other = DaySummary {
+            high: 12.5,
+            low: 1.0,
+            precip: 0.0,
+            ..base
+        };
+        let d = other.diff(&base);
+        assert_eq!(d.date, base.date);
+        assert!((d.high - 2.5).abs() < 1e-9);
+        assert!((d.low + 1.0).abs() < 1e-9);
+        assert!((d.precip + 1.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,529 @@
+use anyhow::{format_err, Error};
+use dioxus::prelude::{dioxus_elements, format_args_f, rsx, LazyNodes, NodeFactory, VNode};
+use stack_string::{format_sstr, StackString};
+use std::str::FromStr;
+use time::{format_description::well_known::Rfc3339, macros::format_description, UtcOffset};
+use weather_util_rust::{weather_data::WeatherData, weather_forecast::WeatherForecast};
+
+use crate::{
+    chart::forecast_chart,
+    current::Clouds,
+    entry::WeatherEntry,
+    hourly::{compass_point, HourlyForecast},
+    icons::icon_src,
+    units::{PrecipitationUnit, UnitSystem},
+};
+
+/// Which forecast is shown below the current conditions
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ForecastView {
+    #[default]
+    Daily,
+    Hourly,
+    Chart,
+}
+
+impl ForecastView {
+    pub const ALL: [Self; 3] = [Self::Daily, Self::Hourly, Self::Chart];
+
+    #[must_use]
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Daily => "Daily",
+            Self::Hourly => "Hourly",
+            Self::Chart => "Chart",
+        }
+    }
+}
+
+impl FromStr for ForecastView {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|v| v.to_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format_err!("Invalid forecast view {s}"))
+    }
+}
+
+#[must_use]
+pub fn loading_spinner<'a>() -> LazyNodes<'a, 'a> {
+    rsx!(
+        div { class: "absolute top-2 right-2",
+            svg { class: "animate-spin h-5 w-5 text-white",
+                "viewBox": "0 0 24 24",
+                fill: "none",
+                xmlns: "http://www.w3.org/2000/svg",
+                circle { class: "opacity-25",
+                    cx: "12",
+                    cy: "12",
+                    r: "10",
+                    stroke: "currentColor",
+                    "stroke-width": "4",
+                }
+                path { class: "opacity-75",
+                    fill: "currentColor",
+                    d: "M4 12a8 8 0 018-8V0C5.373 0 0 5.373 0 12h4z",
+                }
+            }
+        }
+    )
+}
+
+#[must_use]
+pub fn loading_skeleton<'a>() -> LazyNodes<'a, 'a> {
+    let spinner_element = loading_spinner();
+    rsx!(
+        div { class: "px-6 py-6 relative animate-pulse",
+            spinner_element,
+            div { class: "flex mb-4 justify-between items-center",
+                div { class: "h-6 w-32 bg-gray-400 rounded" }
+                div { class: "h-10 w-20 bg-gray-400 rounded" }
+            }
+            div { class: "h-4 w-full bg-gray-400 rounded mb-2" }
+            div { class: "h-4 w-full bg-gray-400 rounded mb-2" }
+        }
+        div { class: "px-6 py-6 relative animate-pulse",
+            div { class: "h-24 w-full bg-gray-400 rounded" }
+        }
+    )
+}
+
+#[must_use]
+pub fn country_data<'a>(weather: &'a WeatherData, units: UnitSystem) -> LazyNodes<'a, 'a> {
+    let temp = units.temperature.convert(weather.main.temp);
+    let feels = units.temperature.convert(weather.main.feels_like);
+    let min = units.temperature.convert(weather.main.temp_min);
+    let max = units.temperature.convert(weather.main.temp_max);
+    let wind = units.speed.convert(weather.wind.speed);
+    let t_unit = units.temperature.suffix();
+    let s_unit = units.speed.suffix();
+
+    rsx!(
+        div { class: "block sm:flex justify-between items-center flex-wrap",
+            div { class: "w-full sm:w-1/2",
+                div { class: "flex mb-2 justify-between items-center",
+                    span { "Temp" }
+                    small { class: "px-2 inline-block", "{temp:0.2}{t_unit}" }
+                }
+            }
+            div { class: "w-full sm:w-1/2",
+                div { class: "flex mb-2 justify-between items-center",
+                    span { "Feels like" }
+                    small { class: "px-2 inline-block", "{feels:0.2}{t_unit}" }
+                }
+            }
+            div { class: "w-full sm:w-1/2",
+                div { class: "flex mb-2 justify-between items-center",
+                    span { "Temp min" }
+                    small { class: "px-2 inline-block", "{min:0.2}{t_unit}" }
+                }
+            }
+            div { class: "w-full sm:w-1/2",
+                div { class: "flex mb-2 justify-between items-center",
+                    span { "Temp max" }
+                    small { class: "px-2 inline-block", "{max:0.2}{t_unit}" }
+                }
+            }
+            div { class: "w-full sm:w-1/2",
+                div { class: "flex mb-2 justify-between items-center",
+                    span { "Wind" }
+                    small { class: "px-2 inline-block", "{wind:0.1} {s_unit}" }
+                }
+            }
+        }
+    )
+}
+
+/// Collapsible panel with the rest of the current conditions; sunrise and
+/// sunset are shown in the location's own timezone.  `clouds` is kept apart
+/// from `weather` as `WeatherData` has no cloud cover.
+#[must_use]
+pub fn weather_details<'a>(
+    weather: &'a WeatherData,
+    clouds: Option<Clouds>,
+    units: UnitSystem,
+) -> LazyNodes<'a, 'a> {
+    let hm = format_description!("[hour]:[minute]");
+    let sunrise = weather.get_sunrise().format(hm).unwrap_or_default();
+    let sunset = weather.get_sunset().format(hm).unwrap_or_default();
+    let humidity = weather.main.humidity;
+    let pressure = weather.main.pressure.hpa();
+    let s_unit = units.speed.suffix();
+    let p_unit = units.precipitation.suffix();
+    let wind = units.speed.convert(weather.wind.speed);
+    let direction = weather.wind.deg.map_or("", compass_point);
+    // Wind direction is where the wind comes from, the arrow shows where it goes
+    let arrow = weather.wind.deg.map(|d| d.deg() + 180.0);
+    let cloud_cover = clouds.map_or_else(|| "n/a".into(), |c| format_sstr!("{}%", c.all));
+    // There is no distance unit setting, so visibility is in miles along with
+    // inches of precipitation and in kilometers along with millimeters
+    let visibility = weather.visibility.map_or_else(
+        || "n/a".into(),
+        |v| {
+            if units.precipitation == PrecipitationUnit::Inches {
+                format_sstr!("{:0.1} mi", v.miles())
+            } else {
+                format_sstr!("{:0.1} km", v.meters() / 1000.0)
+            }
+        },
+    );
+    let rain = weather
+        .rain
+        .and_then(|r| r.three_hour)
+        .map(|r| format_sstr!("{:0.1}{p_unit}", units.precipitation.convert(r)));
+    let snow = weather
+        .snow
+        .and_then(|s| s.three_hour)
+        .map(|s| format_sstr!("{:0.1}{p_unit}", units.precipitation.convert(s)));
+
+    rsx!(
+        details { class: "mt-2",
+            summary { class: "cursor-pointer text-sm", "Details" }
+            div { class: "block sm:flex justify-between items-center flex-wrap mt-2",
+                div { class: "w-full sm:w-1/2",
+                    div { class: "flex mb-2 justify-between items-center",
+                        span { "Humidity" }
+                        small { class: "px-2 inline-block", "{humidity}%" }
+                    }
+                }
+                div { class: "w-full sm:w-1/2",
+                    div { class: "flex mb-2 justify-between items-center",
+                        span { "Pressure" }
+                        small { class: "px-2 inline-block", "{pressure:0.0} hPa" }
+                    }
+                }
+                div { class: "w-full sm:w-1/2",
+                    div { class: "flex mb-2 justify-between items-center",
+                        span { "Wind" }
+                        small { class: "px-2 inline-flex items-center",
+                            arrow.map(|rotation| rsx!(
+                                svg { class: "w-4 h-4 mr-1",
+                                    "viewBox": "0 0 24 24",
+                                    xmlns: "http://www.w3.org/2000/svg",
+                                    path {
+                                        transform: "rotate({rotation:0.0} 12 12)",
+                                        fill: "currentColor",
+                                        d: "M12 2l5 18-5-4-5 4z",
+                                    }
+                                }
+                            ))
+                            "{wind:0.1} {s_unit} {direction}"
+                        }
+                    }
+                }
+                div { class: "w-full sm:w-1/2",
+                    div { class: "flex mb-2 justify-between items-center",
+                        span { "Cloud cover" }
+                        small { class: "px-2 inline-block", "{cloud_cover}" }
+                    }
+                }
+                div { class: "w-full sm:w-1/2",
+                    div { class: "flex mb-2 justify-between items-center",
+                        span { "Visibility" }
+                        small { class: "px-2 inline-block", "{visibility}" }
+                    }
+                }
+                div { class: "w-full sm:w-1/2",
+                    div { class: "flex mb-2 justify-between items-center",
+                        span { "Sunrise" }
+                        small { class: "px-2 inline-block", "{sunrise}" }
+                    }
+                }
+                div { class: "w-full sm:w-1/2",
+                    div { class: "flex mb-2 justify-between items-center",
+                        span { "Sunset" }
+                        small { class: "px-2 inline-block", "{sunset}" }
+                    }
+                }
+                rain.map(|rain| rsx!(
+                    div { class: "w-full sm:w-1/2",
+                        div { class: "flex mb-2 justify-between items-center",
+                            span { "Rain" }
+                            small { class: "px-2 inline-block", "{rain}" }
+                        }
+                    }
+                ))
+                snow.map(|snow| rsx!(
+                    div { class: "w-full sm:w-1/2",
+                        div { class: "flex mb-2 justify-between items-center",
+                            span { "Snow" }
+                            small { class: "px-2 inline-block", "{snow}" }
+                        }
+                    }
+                ))
+            }
+        }
+    )
+}
+
+#[must_use]
+pub fn country_info<'a>(weather: &'a WeatherData, units: UnitSystem) -> LazyNodes<'a, 'a> {
+    let name = &weather.name;
+    let country = weather.sys.country.as_ref().map_or("", StackString::as_str);
+    let mut main = StackString::new();
+    let mut desc = StackString::new();
+    let mut icon = StackString::new();
+    if let Some(weather) = weather.weather.first() {
+        main.push_str(&weather.main);
+        desc.push_str(&weather.description);
+        icon.push_str(&weather.icon);
+    }
+    let icon = icon_src(&icon);
+    let temp = units.temperature.convert(weather.main.temp);
+    let t_unit = units.temperature.suffix();
+    let fo: UtcOffset = weather.timezone.into();
+    let date = weather
+        .dt
+        .to_offset(fo)
+        .format(&Rfc3339)
+        .unwrap_or_default();
+
+    rsx!(
+        div { class: "flex mb-4 justify-between items-center",
+            div {
+                h5 { class: "mb-0 font-medium text-xl",
+                    "{name} {country}"
+                }
+                span { class: "block text-sm",
+                    "{main} - {desc}"
+                }
+                small {
+                    img { class: "block w-8 h-8",
+                        src: "{icon}",
+                        alt: "{desc}",
+                    }
+                }
+            }
+            div { class: "text-right",
+                h6 { class: "mb-0",
+                    "{date}"
+                }
+                h3 { class: "font-bold text-4xl mb-0",
+                    span {
+                        "{temp:0.1}{t_unit}"
+                    }
+                }
+            }
+        }
+    )
+}
+
+#[must_use]
+pub fn week_weather<'a>(forecast: &'a WeatherForecast, units: UnitSystem) -> LazyNodes<'a, 'a> {
+    let high_low = forecast.get_high_low();
+    let t_unit = units.temperature.suffix();
+    let p_unit = units.precipitation.suffix();
+    rsx!(
+        div { class: "divider table mx-2 text-center bg-transparent whitespace-nowrap",
+            span { class: "inline-block px-3", small { "Forecast" } }
+        }
+        div { class: "px-6 py-6 relative",
+            div { class: "text-center justify-between items-center flex",
+                style: "flex-flow: initial;",
+                high_low.iter().map(|(d, (h, l, r, s, i))| {
+                    let weekday = d.weekday();
+                    let low = units.temperature.convert(*l);
+                    let high = units.temperature.convert(*h);
+                    let mut rain = StackString::new();
+                    let mut snow = StackString::new();
+                    if r.millimeters() > 0.0 {
+                        rain = format_sstr!("R {:0.1}{p_unit}", units.precipitation.convert(*r));
+                    }
+                    if s.millimeters() > 0.0 {
+                        snow = format_sstr!("S {:0.1}{p_unit}", units.precipitation.convert(*s));
+                    }
+                    let mut icon = StackString::new();
+                    if let Some(i) = i.iter().next() {
+                        icon.push_str(i);
+                    }
+                    let icon = icon_src(&icon);
+
+                    rsx!(div {
+                            key: "weather-forecast-key-{d}",
+                            class: "text-center mb-0 flex items-center justify-center flex-col",
+                            span { class: "block my-1",
+                                "{weekday}"
+                            }
+                            img { class: "block w-8 h-8",
+                                src: "{icon}",
+                            }
+                            span { class: "block my-1",
+                                "{low:0.1}{t_unit}"
+                            }
+                            span { class: "block my-1",
+                                "{high:0.1}{t_unit}"
+                            }
+                            span { class: "block my-1",
+                                "{rain}"
+                            }
+                            span { class: "block my-1",
+                                "{snow}"
+                            }
+                        }
+                    )
+                })
+            }
+        }
+    )
+}
+
+#[must_use]
+pub fn hourly_weather<'a>(hourly: HourlyForecast, units: UnitSystem) -> LazyNodes<'a, 'a> {
+    let fo: UtcOffset = hourly.city.timezone.into();
+    let t_unit = units.temperature.suffix();
+    let p_unit = units.precipitation.suffix();
+    let s_unit = units.speed.suffix();
+    rsx!(
+        div { class: "divider table mx-2 text-center bg-transparent whitespace-nowrap",
+            span { class: "inline-block px-3", small { "Hourly" } }
+        }
+        div { class: "px-6 py-6 relative",
+            div { class: "flex overflow-x-auto text-center",
+                hourly.list.iter().enumerate().map(|(idx, entry)| {
+                    let dt = entry.dt.to_offset(fo);
+                    let new_day = idx == 0 || dt.hour() < 3;
+                    let weekday = if new_day { format_sstr!("{}", dt.weekday()) } else { StackString::new() };
+                    let time = dt
+                        .format(format_description!("[hour]:[minute]"))
+                        .unwrap_or_default();
+                    let temp = units.temperature.convert(entry.main.temp);
+                    let mut icon = StackString::new();
+                    if let Some(weather) = entry.weather.first() {
+                        icon.push_str(&weather.icon);
+                    }
+                    let icon = icon_src(&icon);
+                    let pop = entry.pop.map_or_else(StackString::new, |p| format_sstr!("{:0.0}%", p * 100.0));
+                    let precip = entry
+                        .rain
+                        .as_ref()
+                        .and_then(|r| r.three_hour)
+                        .into_iter()
+                        .chain(entry.snow.as_ref().and_then(|s| s.three_hour))
+                        .reduce(|a, b| a + b)
+                        .filter(|p| p.millimeters() > 0.0)
+                        .map_or_else(StackString::new, |p| {
+                            format_sstr!("{:0.1}{p_unit}", units.precipitation.convert(p))
+                        });
+                    let wind = entry.wind.as_ref().map_or_else(StackString::new, |w| {
+                        let speed = units.speed.convert(w.speed);
+                        let direction = w.deg.map_or("", compass_point);
+                        format_sstr!("{speed:0.0} {s_unit} {direction}")
+                    });
+
+                    rsx!(div {
+                            key: "weather-hourly-key-{entry.dt}",
+                            class: "flex-none w-20 mb-0 flex items-center flex-col",
+                            span { class: "block my-1 text-xs h-4",
+                                "{weekday}"
+                            }
+                            span { class: "block my-1",
+                                "{time}"
+                            }
+                            img { class: "block w-8 h-8",
+                                src: "{icon}",
+                            }
+                            span { class: "block my-1",
+                                "{temp:0.1}{t_unit}"
+                            }
+                            span { class: "block my-1 text-xs h-4",
+                                "{pop}"
+                            }
+                            span { class: "block my-1 text-xs h-4",
+                                "{precip}"
+                            }
+                            span { class: "block my-1 text-xs h-4",
+                                "{wind}"
+                            }
+                        }
+                    )
+                })
+            }
+        }
+    )
+}
+
+/// The card for the selected location: current conditions, `view_tabs` (the
+/// app's buttons switching the forecast view) and the forecast in `view`.
+/// Until `entry` has both weather and forecast it shows a skeleton while
+/// `loading`, and a notice once the lookup is done, rather than another
+/// location's data.
+#[must_use]
+pub fn weather_card<'a>(
+    entry: Option<&'a WeatherEntry>,
+    units: UnitSystem,
+    view: ForecastView,
+    loading: bool,
+    view_tabs: Vec<LazyNodes<'a, 'a>>,
+) -> LazyNodes<'a, 'a> {
+    let data = entry.and_then(|e| Some((e, e.weather.as_ref()?, e.forecast.as_ref()?)));
+    let card_element = match data {
+        Some((entry, weather, forecast)) => {
+            let spinner_element = if loading {
+                Some(loading_spinner())
+            } else {
+                None
+            };
+            let country_info_element = country_info(weather, units);
+            let country_data_element = country_data(weather, units);
+            let weather_details_element = weather_details(weather, entry.clouds, units);
+            let forecast_element = match view {
+                ForecastView::Daily => week_weather(forecast, units),
+                ForecastView::Chart => forecast_chart(forecast, units),
+                ForecastView::Hourly => {
+                    hourly_weather(entry.get_hourly().unwrap_or_else(|| forecast.into()), units)
+                }
+            };
+            rsx!(
+                div { class: "px-6 py-6 relative",
+                    spinner_element,
+                    country_info_element,
+                    country_data_element,
+                    weather_details_element,
+                }
+                div { class: "flex justify-center",
+                    view_tabs.into_iter()
+                }
+                forecast_element,
+            )
+        }
+        None if loading => loading_skeleton(),
+        None => rsx!(
+            div { class: "px-6 py-6 relative text-center",
+                "No weather data available"
+            }
+        ),
+    };
+
+    rsx!(
+        div { class: "flex flex-wrap w-full px-2",
+            div { class: "bg-gray-900 text-white relative min-w-0 break-words rounded-lg overflow-hidden shadow-sm mb-4 w-full bg-white dark:bg-gray-600",
+                card_element
+            }
+        }
+    )
+}
+
+/// Page of the web and desktop apps: the search `controls` on top, then the
+/// `notices` (errors, alerts and the like), the weather `card` and anything
+/// `below` it.
+#[must_use]
+pub fn page_layout<'a>(
+    controls: LazyNodes<'a, 'a>,
+    notices: Vec<LazyNodes<'a, 'a>>,
+    card: LazyNodes<'a, 'a>,
+    below: Vec<LazyNodes<'a, 'a>>,
+) -> LazyNodes<'a, 'a> {
+    rsx!(
+        div { class: "mx-auto p-4 bg-gray-100 h-screen flex justify-center",
+            div { class: "flex items-center justify-center flex-col",
+                div {
+                    controls
+                }
+                notices.into_iter(),
+                card
+                below.into_iter(),
+            }
+        }
+    )
+}
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+use weather_util_rust::weather_data::WeatherData;
+
+/// Cloud cover of the sky, in percent
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clouds {
+    pub all: u8,
+}
+
+/// Current conditions, keeping the cloud cover that `WeatherData` drops.
+/// Deserialized from the same response as `WeatherData`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct CurrentWeather {
+    #[serde(flatten)]
+    pub weather: WeatherData,
+    #[serde(default)]
+    pub clouds: Option<Clouds>,
+}
+
+impl From<WeatherData> for CurrentWeather {
+    fn from(weather: WeatherData) -> Self {
+        Self {
+            weather,
+            clouds: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use weather_util_rust::weather_data::WeatherData;
+
+    use crate::current::{Clouds, CurrentWeather};
+
+    #[test]
+    fn test_current_weather() {
+        let buf = include_str!("../tests/weather.json");
+        let current: CurrentWeather = serde_json::from_str(buf).unwrap();
+        assert_eq!(current.clouds, Some(Clouds { all: 75 }));
+        let weather: WeatherData = serde_json::from_str(buf).unwrap();
+        assert_eq!(current.weather, weather);
+
+        let without_clouds = buf.replace(r#""clouds":{"all":75},"#, "");
+        let current: CurrentWeather = serde_json::from_str(&without_clouds).unwrap();
+        assert_eq!(current, CurrentWeather::from(weather));
+    }
+}
@@ -0,0 +1,175 @@
+use stack_string::{format_sstr, StackString};
+use std::fmt;
+use url::Url;
+
+/// Path of the openweathermap geocoding api, used by both apps unless
+/// configured otherwise.
+pub static DEFAULT_GEOCODE_PATH: &str = "geo/1.0/direct";
+
+/// Where the active api endpoint came from, in order of precedence
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EndpointSource {
+    QueryParam,
+    MetaTag,
+    JsonConfig,
+    BuildEnv,
+    Default,
+}
+
+impl fmt::Display for EndpointSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::QueryParam => "query parameter",
+            Self::MetaTag => "meta tag",
+            Self::JsonConfig => "json config",
+            Self::BuildEnv => "build environment",
+            Self::Default => "default",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiEndpoint {
+    pub url: StackString,
+    pub source: EndpointSource,
+}
+
+impl ApiEndpoint {
+    /// Pick the first candidate that is a valid http(s) url, falling back to
+    /// `default`; candidates should be given in order of precedence.
+    #[must_use]
+    pub fn resolve<'a>(
+        candidates: impl IntoIterator<Item = (EndpointSource, Option<&'a str>)>,
+        default: &str,
+    ) -> Self {
+        candidates
+            .into_iter()
+            .find_map(|(source, url)| {
+                url.and_then(normalize_endpoint)
+                    .map(|url| Self { url, source })
+            })
+            .unwrap_or_else(|| Self {
+                url: normalize_endpoint(default).unwrap_or_else(|| default.into()),
+                source: EndpointSource::Default,
+            })
+    }
+}
+
+/// Api commands are appended to the endpoint, so it must end in a slash.
+#[must_use]
+pub fn normalize_endpoint(url: &str) -> Option<StackString> {
+    let url = Url::parse(url.trim()).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    let url = url.as_str();
+    if url.ends_with('/') {
+        Some(url.into())
+    } else {
+        Some(format_sstr!("{url}/"))
+    }
+}
+
+/// Pick the first non empty geocoding path, falling back to
+/// `DEFAULT_GEOCODE_PATH`; candidates should be given in order of precedence.
+/// The path is appended to the endpoint, so leading slashes are dropped.
+#[must_use]
+pub fn resolve_geocode_path<'a>(
+    candidates: impl IntoIterator<Item = Option<&'a str>>,
+) -> StackString {
+    candidates
+        .into_iter()
+        .flatten()
+        .map(|path| path.trim().trim_start_matches('/'))
+        .find(|path| !path.is_empty())
+        .unwrap_or(DEFAULT_GEOCODE_PATH)
+        .into()
+}
+
+/// Pick the first candidate that is a positive number of seconds, falling
+/// back to `default`; candidates should be given in order of precedence.
+#[must_use]
+pub fn resolve_seconds<'a>(
+    candidates: impl IntoIterator<Item = Option<&'a str>>,
+    default: u32,
+) -> u32 {
+    candidates
+        .into_iter()
+        .flatten()
+        .filter_map(|s| s.trim().parse().ok())
+        .find(|seconds| *seconds > 0)
+        .unwrap_or(default)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::endpoint::{
+        normalize_endpoint, resolve_geocode_path, resolve_seconds, ApiEndpoint, EndpointSource,
+        DEFAULT_GEOCODE_PATH,
+    };
+
+    static DEFAULT: &str = "https://cloud.ddboline.net/weather/";
+
+    #[test]
+    fn test_normalize_endpoint() {
+        assert_eq!(
+            normalize_endpoint(" http://localhost:8080/weather "),
+            Some("http://localhost:8080/weather/".into())
+        );
+        assert_eq!(normalize_endpoint(DEFAULT), Some(DEFAULT.into()));
+        assert_eq!(normalize_endpoint(""), None);
+        assert_eq!(normalize_endpoint("localhost:8080"), None);
+        assert_eq!(normalize_endpoint("ftp://example.com/"), None);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let endpoint = ApiEndpoint::resolve(
+            [
+                (EndpointSource::QueryParam, None),
+                (EndpointSource::MetaTag, Some("")),
+                (
+                    EndpointSource::JsonConfig,
+                    Some("https://proxy.example.com/api"),
+                ),
+                (EndpointSource::BuildEnv, Some("http://localhost:3000/")),
+            ],
+            DEFAULT,
+        );
+        assert_eq!(endpoint.url, "https://proxy.example.com/api/");
+        assert_eq!(endpoint.source, EndpointSource::JsonConfig);
+
+        let endpoint = ApiEndpoint::resolve(
+            [
+                (EndpointSource::QueryParam, Some("not a url")),
+                (EndpointSource::BuildEnv, None),
+            ],
+            DEFAULT,
+        );
+        assert_eq!(endpoint.url, DEFAULT);
+        assert_eq!(endpoint.source, EndpointSource::Default);
+    }
+
+    #[test]
+    fn test_resolve_seconds() {
+        assert_eq!(
+            resolve_seconds([None, Some("0"), Some("ten"), Some(" 300 ")], 600),
+            300
+        );
+        assert_eq!(resolve_seconds([Some("-5"), None], 600), 600);
+    }
+
+    #[test]
+    fn test_resolve_geocode_path() {
+        assert_eq!(
+            resolve_geocode_path([None, Some(" ")]),
+            DEFAULT_GEOCODE_PATH
+        );
+        assert_eq!(
+            resolve_geocode_path([None, Some("/geo/1.0/direct"), Some("direct")]),
+            "geo/1.0/direct"
+        );
+        assert_eq!(resolve_geocode_path([Some("direct")]), "direct");
+    }
+}
@@ -0,0 +1,255 @@
+use serde::{Deserialize, Serialize};
+use time::{Duration, OffsetDateTime};
+use weather_util_rust::{
+    default_datetime, weather_data::WeatherData, weather_forecast::WeatherForecast,
+};
+
+use crate::{alerts::WeatherAlert, current::Clouds, error::WeatherError, hourly::HourlyForecast};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WeatherEntry {
+    pub weather: Option<WeatherData>,
+    pub forecast: Option<WeatherForecast>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hourly: Option<HourlyForecast>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clouds: Option<Clouds>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alerts: Vec<WeatherAlert>,
+    #[serde(default = "default_datetime")]
+    pub fetched_at: OffsetDateTime,
+    #[serde(skip)]
+    pub error: Option<WeatherError>,
+    /// Answered from the service worker's copy of an earlier response
+    #[serde(skip)]
+    pub offline: bool,
+}
+
+impl WeatherEntry {
+    #[must_use]
+    pub fn new(weather: Option<WeatherData>, forecast: Option<WeatherForecast>) -> Self {
+        Self {
+            weather,
+            forecast,
+            hourly: None,
+            clouds: None,
+            alerts: Vec::new(),
+            fetched_at: OffsetDateTime::now_utc(),
+            error: None,
+            offline: false,
+        }
+    }
+
+    #[must_use]
+    pub fn with_hourly(self, hourly: Option<HourlyForecast>) -> Self {
+        Self { hourly, ..self }
+    }
+
+    /// Hourly slots, falling back to the plain forecast (without wind or
+    /// precipitation probability) for entries cached before they were kept.
+    #[must_use]
+    pub fn get_hourly(&self) -> Option<HourlyForecast> {
+        self.hourly
+            .clone()
+            .or_else(|| self.forecast.as_ref().map(Into::into))
+    }
+
+    #[must_use]
+    pub fn with_clouds(self, clouds: Option<Clouds>) -> Self {
+        Self { clouds, ..self }
+    }
+
+    #[must_use]
+    pub fn with_alerts(self, alerts: Vec<WeatherAlert>) -> Self {
+        Self { alerts, ..self }
+    }
+
+    #[must_use]
+    pub fn with_error(self, error: Option<WeatherError>) -> Self {
+        Self { error, ..self }
+    }
+
+    /// Mark the data as coming from a response cached at `cached_at`, when
+    /// there is one, rather than from the network just now.
+    #[must_use]
+    pub fn with_cached_at(self, cached_at: Option<OffsetDateTime>) -> Self {
+        match cached_at {
+            Some(fetched_at) => Self {
+                fetched_at,
+                offline: true,
+                ..self
+            },
+            None => self,
+        }
+    }
+
+    /// When the displayed data was fetched, if it is a cached response or
+    /// left over from before the network went down.
+    #[must_use]
+    pub fn offline_since(&self) -> Option<OffsetDateTime> {
+        let network_down = matches!(self.error, Some(WeatherError::Network(_)));
+        ((self.offline || network_down) && self.weather.is_some()).then_some(self.fetched_at)
+    }
+
+    /// Entries older than the ttl are still displayed, but are refetched the
+    /// next time their location is selected.
+    #[must_use]
+    pub fn is_stale(&self, ttl: Duration) -> bool {
+        OffsetDateTime::now_utc() - self.fetched_at > ttl
+    }
+
+    /// A failed refetch keeps whatever data the previous entry already had,
+    /// along with when that was fetched.
+    #[must_use]
+    pub fn or_previous(self, previous: Option<&Self>) -> Self {
+        match previous {
+            Some(previous) if self.error.is_some() => Self {
+                fetched_at: if self.weather.is_none() {
+                    previous.fetched_at
+                } else {
+                    self.fetched_at
+                },
+                offline: self.offline || (self.weather.is_none() && previous.offline),
+                clouds: if self.weather.is_none() {
+                    previous.clouds
+                } else {
+                    self.clouds
+                },
+                alerts: if self.weather.is_none() {
+                    previous.alerts.clone()
+                } else {
+                    self.alerts
+                },
+                weather: self.weather.or_else(|| previous.weather.clone()),
+                forecast: self.forecast.or_else(|| previous.forecast.clone()),
+                hourly: self.hourly.or_else(|| previous.hourly.clone()),
+                ..self
+            },
+            _ => self,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RequestState {
+    Idle,
+    Loading,
+    Loaded,
+    Failed,
+}
+
+impl RequestState {
+    #[must_use]
+    pub fn from_entry(entry: Option<&WeatherEntry>, pending: bool) -> Self {
+        match entry {
+            _ if pending => Self::Loading,
+            Some(WeatherEntry { error: Some(_), .. }) => Self::Failed,
+            Some(_) => Self::Loaded,
+            None => Self::Idle,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::{Duration, OffsetDateTime};
+    use weather_util_rust::weather_data::WeatherData;
+
+    use crate::{
+        alerts::WeatherAlert,
+        current::Clouds,
+        entry::{RequestState, WeatherEntry},
+        error::WeatherError,
+    };
+
+    #[test]
+    fn test_is_stale() {
+        let mut entry = WeatherEntry::new(None, None);
+        assert!(!entry.is_stale(Duration::minutes(10)));
+        entry.fetched_at -= Duration::minutes(11);
+        assert!(entry.is_stale(Duration::minutes(10)));
+    }
+
+    #[test]
+    fn test_or_previous() {
+        let alert = WeatherAlert {
+            sender_name: "NWS Upton NY".into(),
+            event: "Wind Advisory".into(),
+            start: OffsetDateTime::now_utc(),
+            end: OffsetDateTime::now_utc() + Duration::hours(12),
+            description: "West winds 20 to 30 mph.".into(),
+            tags: Vec::new(),
+        };
+        let previous = WeatherEntry::new(Some(WeatherData::default()), None)
+            .with_clouds(Some(Clouds { all: 40 }))
+            .with_alerts(vec![alert]);
+        let entry = WeatherEntry::new(None, None).or_previous(Some(&previous));
+        assert!(entry.weather.is_none());
+        assert!(entry.alerts.is_empty());
+
+        let entry = WeatherEntry::new(None, None)
+            .with_error(Some(WeatherError::HttpStatus(500)))
+            .or_previous(Some(&previous));
+        assert!(entry.weather.is_some());
+        assert_eq!(entry.error, Some(WeatherError::HttpStatus(500)));
+        assert_eq!(entry.fetched_at, previous.fetched_at);
+        assert_eq!(entry.alerts, previous.alerts);
+        assert_eq!(entry.clouds, previous.clouds);
+    }
+
+    #[test]
+    fn test_offline_since() {
+        let cached_at = OffsetDateTime::now_utc() - Duration::hours(2);
+        let entry = WeatherEntry::new(Some(WeatherData::default()), None);
+        assert_eq!(entry.offline_since(), None);
+        assert_eq!(entry.clone().with_cached_at(None), entry);
+
+        let cached = entry.with_cached_at(Some(cached_at));
+        assert!(cached.offline);
+        assert_eq!(cached.offline_since(), Some(cached_at));
+
+        let failed = WeatherEntry::new(None, None)
+            .with_error(Some(WeatherError::Network("offline".into())))
+            .or_previous(Some(&cached));
+        assert_eq!(failed.offline_since(), Some(cached_at));
+
+        let failed = WeatherEntry::new(None, None)
+            .with_error(Some(WeatherError::HttpStatus(500)))
+            .or_previous(Some(&WeatherEntry::new(Some(WeatherData::default()), None)));
+        assert_eq!(failed.offline_since(), None);
+        assert_eq!(
+            WeatherEntry::new(None, None)
+                .with_error(Some(WeatherError::Network("offline".into())))
+                .offline_since(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_request_state() {
+        let entry = WeatherEntry::new(None, None);
+        assert_eq!(RequestState::from_entry(None, false), RequestState::Idle);
+        assert_eq!(
+            RequestState::from_entry(Some(&entry), true),
+            RequestState::Loading
+        );
+        assert_eq!(
+            RequestState::from_entry(Some(&entry), false),
+            RequestState::Loaded
+        );
+        let entry = entry.with_error(Some(WeatherError::HttpStatus(500)));
+        assert_eq!(
+            RequestState::from_entry(Some(&entry), false),
+            RequestState::Failed
+        );
+    }
+
+    #[test]
+    fn test_entry_serde() {
+        let entry: WeatherEntry = serde_json::from_str(r#"{"weather":null,"forecast":null}"#)
+            .expect("Failed to deserialize");
+        assert_eq!(entry.fetched_at, weather_util_rust::default_datetime());
+        assert!(entry.hourly.is_none());
+        assert!(entry.get_hourly().is_none());
+    }
+}
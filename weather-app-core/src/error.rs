@@ -0,0 +1,134 @@
+use stack_string::{format_sstr, StackString};
+use std::fmt::Debug;
+use thiserror::Error;
+use weather_util_rust::weather_api::WeatherLocation;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum WeatherError {
+    #[error("Network error: {0}")]
+    Network(StackString),
+    #[error("HTTP status {0}")]
+    HttpStatus(u16),
+    #[error("JSON decode error: {0}")]
+    JsonDecode(StackString),
+    #[error("Unknown location {0}")]
+    UnknownLocation(StackString),
+}
+
+impl WeatherError {
+    pub fn network(e: impl Debug) -> Self {
+        Self::Network(format_sstr!("{e:?}"))
+    }
+
+    pub fn json_decode(e: impl Debug) -> Self {
+        Self::JsonDecode(format_sstr!("{e:?}"))
+    }
+
+    /// The api answers a lookup it cannot resolve with a 404
+    #[must_use]
+    pub fn for_location(self, location: &WeatherLocation) -> Self {
+        match self {
+            Self::HttpStatus(404) => Self::UnknownLocation(format_sstr!("{location}")),
+            e => e,
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    #[error("Empty location")]
+    Empty,
+    #[error("Invalid zipcode {0}")]
+    InvalidZipcode(StackString),
+    #[error("Invalid country code {0}")]
+    InvalidCountryCode(StackString),
+    #[error("Invalid latitude {0}")]
+    InvalidLatitude(StackString),
+    #[error("Invalid longitude {0}")]
+    InvalidLongitude(StackString),
+    #[error("Unknown parameter {0}")]
+    UnknownParameter(StackString),
+    #[error("Missing parameter {0}")]
+    MissingParameter(&'static str),
+    #[error("Ambiguous location {0}")]
+    Ambiguous(StackString),
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum StorageError {
+    #[error("Corrupt stored data: {0}")]
+    Corrupt(StackString),
+    #[error("Stored data version {0} is newer than supported version {1}")]
+    UnsupportedVersion(u64, u64),
+}
+
+impl StorageError {
+    pub fn corrupt(e: impl Debug) -> Self {
+        Self::Corrupt(format_sstr!("{e:?}"))
+    }
+}
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeolocationError {
+    #[error("Location permission denied")]
+    PermissionDenied,
+    #[error("Position unavailable")]
+    PositionUnavailable,
+    #[error("Location request timed out")]
+    Timeout,
+    #[error("Geolocation not supported")]
+    Unsupported,
+}
+
+impl GeolocationError {
+    /// Maps the `code` of a browser `GeolocationPositionError`
+    #[must_use]
+    pub fn from_code(code: u16) -> Self {
+        match code {
+            1 => Self::PermissionDenied,
+            3 => Self::Timeout,
+            _ => Self::PositionUnavailable,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use weather_util_rust::weather_api::WeatherLocation;
+
+    use crate::error::{GeolocationError, WeatherError};
+
+    #[test]
+    fn test_for_location() {
+        let loc = WeatherLocation::from_zipcode(11106);
+        assert_eq!(
+            WeatherError::HttpStatus(404).for_location(&loc),
+            WeatherError::UnknownLocation("11106".into())
+        );
+        assert_eq!(
+            WeatherError::HttpStatus(500).for_location(&loc),
+            WeatherError::HttpStatus(500)
+        );
+        assert_eq!(
+            format!("{}", WeatherError::HttpStatus(500)),
+            "HTTP status 500"
+        );
+    }
+
+    #[test]
+    fn test_geolocation_error_from_code() {
+        assert_eq!(
+            GeolocationError::from_code(1),
+            GeolocationError::PermissionDenied
+        );
+        assert_eq!(
+            GeolocationError::from_code(2),
+            GeolocationError::PositionUnavailable
+        );
+        assert_eq!(GeolocationError::from_code(3), GeolocationError::Timeout);
+        assert_eq!(
+            GeolocationError::from_code(0),
+            GeolocationError::PositionUnavailable
+        );
+    }
+}
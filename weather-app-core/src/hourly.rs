@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use weather_util_rust::{
+    direction::Direction,
+    timestamp,
+    weather_data::{Rain, Snow, WeatherCond, Wind},
+    weather_forecast::{CityEntry, ForecastEntry, ForecastMain, WeatherForecast},
+};
+
+/// A single 3 hour slot of the forecast, keeping the wind and probability of
+/// precipitation that `ForecastEntry` drops.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct HourlyEntry {
+    #[serde(with = "timestamp")]
+    pub dt: OffsetDateTime,
+    pub main: ForecastMain,
+    pub weather: Vec<WeatherCond>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wind: Option<Wind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pop: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rain: Option<Rain>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snow: Option<Snow>,
+}
+
+/// The 5 day / 3 hour forecast, deserialized from the same response as
+/// `WeatherForecast`.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct HourlyForecast {
+    pub list: Vec<HourlyEntry>,
+    pub city: CityEntry,
+}
+
+impl From<&HourlyForecast> for WeatherForecast {
+    fn from(item: &HourlyForecast) -> Self {
+        Self {
+            list: item
+                .list
+                .iter()
+                .map(|entry| ForecastEntry {
+                    dt: entry.dt,
+                    main: entry.main,
+                    weather: entry.weather.clone(),
+                    rain: entry.rain,
+                    snow: entry.snow,
+                })
+                .collect(),
+            city: item.city,
+        }
+    }
+}
+
+impl From<&WeatherForecast> for HourlyForecast {
+    fn from(item: &WeatherForecast) -> Self {
+        Self {
+            list: item
+                .list
+                .iter()
+                .map(|entry| HourlyEntry {
+                    dt: entry.dt,
+                    main: entry.main,
+                    weather: entry.weather.clone(),
+                    wind: None,
+                    pop: None,
+                    rain: entry.rain,
+                    snow: entry.snow,
+                })
+                .collect(),
+            city: item.city,
+        }
+    }
+}
+
+/// Sixteen point compass label for a wind direction
+#[must_use]
+pub fn compass_point(direction: Direction) -> &'static str {
+    const POINTS: [&str; 16] = [
+        "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW",
+        "NW", "NNW",
+    ];
+    let deg = direction.deg().rem_euclid(360.0);
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let index = ((deg / 22.5).round() as usize) % POINTS.len();
+    POINTS[index]
+}
+
+#[cfg(test)]
+mod tests {
+    use weather_util_rust::{direction::Direction, weather_forecast::WeatherForecast};
+
+    use crate::hourly::{compass_point, HourlyForecast};
+
+    #[test]
+    fn test_hourly_forecast() {
+        let buf = r#"{"list":[{"dt":1646017200,"main":{"temp":276.76,"feels_like":271.8,"temp_min":275.01,"temp_max":276.76,"pressure":1015.0,"sea_level":1015.0,"grnd_level":1015.0,"humidity":41},"weather":[{"id":803,"main":"Clouds","description":"broken clouds","icon":"04n"}],"wind":{"speed":4.1,"deg":250,"gust":7.2},"pop":0.35,"rain":{"3h":0.4}}],"city":{"timezone":-18000,"sunrise":1645962587,"sunset":1646003106}}"#;
+        let hourly: HourlyForecast = serde_json::from_str(buf).unwrap();
+        let entry = &hourly.list[0];
+        assert_eq!(entry.pop, Some(0.35));
+        let wind = entry.wind.as_ref().unwrap();
+        assert!((wind.speed.mps() - 4.1).abs() < 1e-6);
+        assert_eq!(wind.deg.map(compass_point), Some("WSW"));
+
+        let forecast: WeatherForecast = serde_json::from_str(buf).unwrap();
+        assert_eq!(WeatherForecast::from(&hourly), forecast);
+        let without_wind = HourlyForecast::from(&forecast);
+        assert_eq!(without_wind.list[0].wind, None);
+        assert_eq!(without_wind.list[0].pop, None);
+        assert_eq!(without_wind.list[0].main, entry.main);
+    }
+
+    #[test]
+    fn test_compass_point() {
+        assert_eq!(compass_point(Direction::from_deg(0.0)), "N");
+        assert_eq!(compass_point(Direction::from_deg(359.0)), "N");
+        assert_eq!(compass_point(Direction::from_deg(90.0)), "E");
+        assert_eq!(compass_point(Direction::from_deg(225.0)), "SW");
+        assert_eq!(compass_point(Direction::from_deg(-90.0)), "W");
+    }
+}
@@ -0,0 +1,115 @@
+use stack_string::{format_sstr, StackString};
+use std::fmt::Write;
+
+static SUN: &str = "<circle cx='32' cy='32' r='12' fill='#fbbf24'/>\
+    <circle cx='32' cy='32' r='20' fill='none' stroke='#fbbf24' stroke-width='4' \
+    stroke-dasharray='4 6.47'/>";
+static MOON: &str = "<path d='M38 12a20 20 0 1 0 14 32a16 16 0 0 1-14-32z' fill='#d1d5db'/>";
+static CLOUD: &str = "<path d='M18 46h30a10 10 0 0 0 0-20a14 14 0 0 0-27-2a11 11 0 0 0-3 22z' \
+    fill='#f3f4f6' stroke='#9ca3af' stroke-width='2'/>";
+static DARK_CLOUD: &str =
+    "<path d='M18 46h30a10 10 0 0 0 0-20a14 14 0 0 0-27-2a11 11 0 0 0-3 22z' \
+    fill='#9ca3af' stroke='#4b5563' stroke-width='2'/>";
+static BACK_CLOUD: &str = "<path d='M30 34h22a8 8 0 0 0 0-16a11 11 0 0 0-21-1a9 9 0 0 0-1 17z' \
+    fill='#d1d5db' stroke='#6b7280' stroke-width='2'/>";
+static RAIN: &str = "<path d='M24 51l-3 8M33 51l-3 8M42 51l-3 8' stroke='#3b82f6' \
+    stroke-width='3' stroke-linecap='round'/>";
+static LIGHTNING: &str = "<path d='M34 44l-7 10h6l-4 9l11-13h-6l4-6z' fill='#f59e0b'/>";
+static SNOW: &str = "<g fill='#93c5fd'><circle cx='22' cy='54' r='3'/>\
+    <circle cx='32' cy='58' r='3'/><circle cx='42' cy='54' r='3'/></g>";
+static MIST: &str = "<path d='M12 24h40M8 34h48M12 44h40' stroke='#9ca3af' stroke-width='4' \
+    stroke-linecap='round'/>";
+
+/// Shrinks a sun or moon into the top left corner, behind a cloud
+fn small(body: &str) -> StackString {
+    format_sstr!("<g transform='translate(3 1) scale(.6)'>{body}</g>")
+}
+
+/// Svg markup for an openweathermap icon code such as `04n`, or `None` for a
+/// code with no embedded icon.
+#[must_use]
+pub fn icon_svg(code: &str) -> Option<StackString> {
+    let night = code.ends_with('n');
+    let sky = if night { MOON } else { SUN };
+    let body = match code.get(..2)? {
+        "01" => sky.into(),
+        "02" => format_sstr!("{}{CLOUD}", small(sky)),
+        "03" => CLOUD.into(),
+        "04" => format_sstr!("{BACK_CLOUD}{CLOUD}"),
+        "09" => format_sstr!("{DARK_CLOUD}{RAIN}"),
+        "10" => format_sstr!("{}{CLOUD}{RAIN}", small(sky)),
+        "11" => format_sstr!("{DARK_CLOUD}{LIGHTNING}"),
+        "13" => format_sstr!("{CLOUD}{SNOW}"),
+        "50" => MIST.into(),
+        _ => return None,
+    };
+    Some(format_sstr!(
+        "<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 64 64'>{body}</svg>"
+    ))
+}
+
+/// Escapes the characters that are not allowed unencoded in a data uri
+fn data_uri(svg: &str) -> StackString {
+    let mut uri = StackString::from("data:image/svg+xml,");
+    for c in svg.chars() {
+        match c {
+            '#' | '%' | '<' | '>' | '"' => {
+                write!(uri, "%{:02X}", c as u32).unwrap_or(());
+            }
+            c => uri.push(c),
+        }
+    }
+    uri
+}
+
+/// Image source for an openweathermap icon code, embedded so no request
+/// leaves the app.  Codes without an embedded icon are loaded from
+/// openweathermap with the `remote-icons` feature and otherwise drawn as a
+/// plain cloud.
+#[must_use]
+pub fn icon_src(code: &str) -> StackString {
+    match icon_svg(code) {
+        Some(svg) => data_uri(&svg),
+        None if cfg!(feature = "remote-icons") => {
+            format_sstr!("https://openweathermap.org/img/wn/{code}@2x.png")
+        }
+        None => icon_src("03d"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::icons::{icon_src, icon_svg};
+
+    #[test]
+    fn test_icon_svg() {
+        for code in ["01", "02", "03", "04", "09", "10", "11", "13", "50"] {
+            for suffix in ["d", "n"] {
+                let svg = icon_svg(&format!("{code}{suffix}")).unwrap();
+                assert!(svg.starts_with("<svg "));
+                assert!(svg.ends_with("</svg>"));
+            }
+        }
+        assert!(icon_svg("01d").unwrap().contains("#fbbf24"));
+        assert!(!icon_svg("01n").unwrap().contains("#fbbf24"));
+        assert_eq!(icon_svg("03d"), icon_svg("03n"));
+        assert_eq!(icon_svg("99d"), None);
+        assert_eq!(icon_svg(""), None);
+    }
+
+    #[test]
+    fn test_icon_src() {
+        let src = icon_src("10d");
+        assert!(src.starts_with("data:image/svg+xml,%3Csvg "));
+        assert!(src.contains("fill='%23fbbf24'"));
+        assert!(!src.contains(['#', '<', '>', '"']));
+        if cfg!(feature = "remote-icons") {
+            assert_eq!(
+                icon_src("99d"),
+                "https://openweathermap.org/img/wn/99d@2x.png"
+            );
+        } else {
+            assert_eq!(icon_src("99d"), icon_src("03d"));
+        }
+    }
+}
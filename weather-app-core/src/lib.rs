@@ -0,0 +1,38 @@
+#![allow(clippy::too_many_lines)]
+#![allow(clippy::module_name_repetitions)]
+
+//! Location parsing, display models and dioxus components shared by the web
+//! app, the desktop app and weather-index.
+
+/// Government weather alerts and their banners
+pub mod alerts;
+/// Stylesheet bundled with the apps
+pub mod assets;
+/// Inline svg charts of the forecast
+pub mod chart;
+/// Daily forecasts of several locations side by side
+pub mod compare;
+/// Dioxus components rendering current conditions and the forecast
+pub mod components;
+/// Current conditions including cloud cover
+pub mod current;
+/// Resolution of the weather api endpoint
+pub mod endpoint;
+/// Cached weather lookups and their request state
+pub mod entry;
+/// Errors from weather lookups
+pub mod error;
+/// Three hourly forecast slots including wind and precipitation probability
+pub mod hourly;
+/// Embedded svg weather icons keyed by openweathermap icon code
+pub mod icons;
+/// Parsing of search strings into `WeatherLocation`
+pub mod location;
+/// Weather data sources behind a common trait
+pub mod provider;
+/// Query strings of the web app and weather-index
+pub mod route;
+/// Versioned state persisted between visits
+pub mod storage;
+/// Temperature, precipitation and wind speed display units
+pub mod units;
@@ -0,0 +1,383 @@
+use isocountry::CountryCode;
+use stack_string::{format_sstr, StackString};
+use std::convert::TryFrom;
+use url::form_urlencoded;
+use weather_util_rust::{
+    latitude::Latitude, longitude::Longitude, weather_api::WeatherLocation,
+    weather_data::WeatherData,
+};
+
+use crate::error::ParseError;
+
+/// Parse a search string into a `WeatherLocation`.
+///
+/// Accepts a zipcode (`11106`), a zipcode and country code (`10115,DE`),
+/// coordinates (`40.7, -73.9`), a city name optionally followed by state and
+/// country (`Astoria,OR,US`), or the query strings stored in weather-index
+/// history (`zip=10001`, `lat=40.7&lon=-73.9`, `q=Astoria`).
+///
+/// # Errors
+///
+/// Returns a `ParseError` for empty input, out of range coordinates, unknown
+/// country codes or query keys, and input mixing numbers and names.
+pub fn parse_location(search_str: &str) -> Result<WeatherLocation, ParseError> {
+    let search_str = search_str.trim();
+    if search_str.is_empty() {
+        return Err(ParseError::Empty);
+    }
+    if search_str.contains('=') {
+        return parse_query(search_str);
+    }
+    let parts: Vec<&str> = search_str.split(',').map(str::trim).collect();
+    if parts.iter().any(|p| p.is_empty()) {
+        return Err(ParseError::Ambiguous(search_str.into()));
+    }
+    let numeric = |s: &str| s.contains(|c: char| c.is_ascii_digit()) && s.parse::<f64>().is_ok();
+    match parts.as_slice() {
+        [zip] if is_zipcode(zip) => parse_zipcode(zip, None),
+        [single] if numeric(single) => Err(ParseError::Ambiguous(search_str.into())),
+        [zip, country] if is_zipcode(zip) && !numeric(country) => parse_zipcode(zip, Some(country)),
+        [lat, lon] if numeric(lat) && numeric(lon) => parse_lat_lon(lat, lon),
+        parts if parts.len() <= 3 && !parts.iter().any(|p| numeric(p)) => {
+            Ok(WeatherLocation::from_city_name(&parts.join(",")))
+        }
+        _ => Err(ParseError::Ambiguous(search_str.into())),
+    }
+}
+
+/// Lenient form of [`parse_location`]: anything that does not parse is looked
+/// up as a city name.
+#[must_use]
+pub fn get_parameters(search_str: &str) -> WeatherLocation {
+    parse_location(search_str)
+        .unwrap_or_else(|_| WeatherLocation::from_city_name(search_str.trim()))
+}
+
+/// Search string that `parse_location` turns back into `location`
+#[must_use]
+pub fn location_to_search(location: &WeatherLocation) -> StackString {
+    match location {
+        WeatherLocation::ZipCode {
+            zipcode,
+            country_code: None,
+        } => format_sstr!("{zipcode}"),
+        WeatherLocation::ZipCode {
+            zipcode,
+            country_code: Some(country_code),
+        } => format_sstr!("{zipcode},{}", country_code.alpha2()),
+        WeatherLocation::CityName(name) => name.as_str().into(),
+        WeatherLocation::LatLon {
+            latitude,
+            longitude,
+        } => format_sstr!("{latitude},{longitude}"),
+    }
+}
+
+/// Name to show for a location in history: coordinates are replaced with the
+/// place name the api resolved them to (`Astoria, US`), or rounded when there
+/// is none yet; anything the user typed is shown as typed.
+#[must_use]
+pub fn location_label(location: &WeatherLocation, weather: Option<&WeatherData>) -> StackString {
+    match location {
+        WeatherLocation::LatLon {
+            latitude,
+            longitude,
+        } => match weather.filter(|w| !w.name.is_empty()) {
+            Some(WeatherData { name, sys, .. }) => match &sys.country {
+                Some(country) => format_sstr!("{name}, {country}"),
+                None => name.as_str().into(),
+            },
+            None => format_sstr!("{:.2},{:.2}", f64::from(*latitude), f64::from(*longitude)),
+        },
+        _ => location_to_search(location),
+    }
+}
+
+fn is_zipcode(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_digit())
+}
+
+fn parse_zipcode(zip: &str, country: Option<&str>) -> Result<WeatherLocation, ParseError> {
+    let zipcode: u64 = zip
+        .parse()
+        .map_err(|_| ParseError::InvalidZipcode(zip.into()))?;
+    match country {
+        Some(country) => {
+            let country_code = CountryCode::for_alpha2(&country.to_uppercase())
+                .map_err(|_| ParseError::InvalidCountryCode(country.into()))?;
+            Ok(WeatherLocation::from_zipcode_country_code(
+                zipcode,
+                country_code,
+            ))
+        }
+        None => Ok(WeatherLocation::from_zipcode(zipcode)),
+    }
+}
+
+fn parse_lat_lon(lat: &str, lon: &str) -> Result<WeatherLocation, ParseError> {
+    let latitude = lat
+        .parse::<f64>()
+        .ok()
+        .and_then(|l| Latitude::try_from(l).ok())
+        .ok_or_else(|| ParseError::InvalidLatitude(lat.into()))?;
+    let longitude = lon
+        .parse::<f64>()
+        .ok()
+        .and_then(|l| Longitude::try_from(l).ok())
+        .ok_or_else(|| ParseError::InvalidLongitude(lon.into()))?;
+    Ok(WeatherLocation::from_lat_lon(latitude, longitude))
+}
+
+fn parse_query(query: &str) -> Result<WeatherLocation, ParseError> {
+    let mut zip: Option<StackString> = None;
+    let mut country: Option<StackString> = None;
+    let mut lat: Option<StackString> = None;
+    let mut lon: Option<StackString> = None;
+    let mut city: Option<StackString> = None;
+    for (key, value) in form_urlencoded::parse(query.trim_start_matches('?').as_bytes()) {
+        let value = Some(value.trim().into());
+        match key.as_ref() {
+            "zip" | "zipcode" => zip = value,
+            "country_code" | "country" => country = value,
+            "lat" => lat = value,
+            "lon" => lon = value,
+            "q" | "city" | "city_name" => city = value,
+            key => return Err(ParseError::UnknownParameter(key.into())),
+        }
+    }
+    match (zip, lat, lon, city) {
+        (Some(zip), None, None, None) => {
+            parse_zipcode(&zip, country.as_ref().map(StackString::as_str))
+        }
+        (None, Some(lat), Some(lon), None) => parse_lat_lon(&lat, &lon),
+        (None, Some(_), None, None) => Err(ParseError::MissingParameter("lon")),
+        (None, None, Some(_), None) => Err(ParseError::MissingParameter("lat")),
+        (None, None, None, Some(city)) if !city.is_empty() => {
+            Ok(WeatherLocation::from_city_name(&city))
+        }
+        (None, None, None, _) => Err(ParseError::Empty),
+        _ => Err(ParseError::Ambiguous(query.into())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use isocountry::CountryCode;
+    use proptest::prelude::*;
+    use std::convert::TryInto;
+    use weather_util_rust::{weather_api::WeatherLocation, weather_data::WeatherData};
+
+    use crate::{
+        error::ParseError,
+        location::{get_parameters, location_label, location_to_search, parse_location},
+    };
+
+    fn lat_lon(lat: f64, lon: f64) -> WeatherLocation {
+        WeatherLocation::from_lat_lon(lat.try_into().unwrap(), lon.try_into().unwrap())
+    }
+
+    #[test]
+    fn test_get_parameters() {
+        assert_eq!(
+            get_parameters("11106"),
+            WeatherLocation::from_zipcode(11106)
+        );
+        assert_eq!(get_parameters("40.7,-73.9"), lat_lon(40.7, -73.9));
+        assert_eq!(
+            get_parameters("Astoria"),
+            WeatherLocation::from_city_name("Astoria")
+        );
+        assert_eq!(
+            get_parameters("Astoria,US"),
+            WeatherLocation::from_city_name("Astoria,US")
+        );
+        assert_eq!(
+            get_parameters("40.7,Astoria"),
+            WeatherLocation::from_city_name("40.7,Astoria")
+        );
+    }
+
+    #[test]
+    fn test_parse_zipcode() {
+        assert_eq!(
+            parse_location(" 11106 "),
+            Ok(WeatherLocation::from_zipcode(11106))
+        );
+        assert_eq!(
+            parse_location("10115,DE"),
+            Ok(WeatherLocation::from_zipcode_country_code(
+                10115,
+                CountryCode::DEU
+            ))
+        );
+        assert_eq!(
+            parse_location("10115, de"),
+            Ok(WeatherLocation::from_zipcode_country_code(
+                10115,
+                CountryCode::DEU
+            ))
+        );
+        assert_eq!(
+            parse_location("10115,XX"),
+            Err(ParseError::InvalidCountryCode("XX".into()))
+        );
+        assert_eq!(
+            parse_location("99999999999999999999999"),
+            Err(ParseError::InvalidZipcode("99999999999999999999999".into()))
+        );
+    }
+
+    #[test]
+    fn test_parse_lat_lon() {
+        assert_eq!(parse_location("40.7, -73.9"), Ok(lat_lon(40.7, -73.9)));
+        assert_eq!(parse_location(" 40.7 ,-73.9 "), Ok(lat_lon(40.7, -73.9)));
+        assert_eq!(
+            parse_location("95.0,-73.9"),
+            Err(ParseError::InvalidLatitude("95.0".into()))
+        );
+        assert_eq!(
+            parse_location("40.7,-273.9"),
+            Err(ParseError::InvalidLongitude("-273.9".into()))
+        );
+    }
+
+    #[test]
+    fn test_parse_city() {
+        assert_eq!(
+            parse_location("Astoria"),
+            Ok(WeatherLocation::from_city_name("Astoria"))
+        );
+        assert_eq!(
+            parse_location("Astoria, OR, US"),
+            Ok(WeatherLocation::from_city_name("Astoria,OR,US"))
+        );
+        assert_eq!(
+            parse_location("New York"),
+            Ok(WeatherLocation::from_city_name("New York"))
+        );
+        assert_eq!(
+            parse_location("Nan"),
+            Ok(WeatherLocation::from_city_name("Nan"))
+        );
+    }
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(
+            parse_location("zip=10001"),
+            Ok(WeatherLocation::from_zipcode(10001))
+        );
+        assert_eq!(
+            parse_location("zip=10115&country_code=DE"),
+            Ok(WeatherLocation::from_zipcode_country_code(
+                10115,
+                CountryCode::DEU
+            ))
+        );
+        assert_eq!(
+            parse_location("lat=40.7&lon=-73.9"),
+            Ok(lat_lon(40.7, -73.9))
+        );
+        assert_eq!(
+            parse_location("lon=-73.9&lat=40.7"),
+            Ok(lat_lon(40.7, -73.9))
+        );
+        assert_eq!(
+            parse_location("q=New%20York"),
+            Ok(WeatherLocation::from_city_name("New York"))
+        );
+        assert_eq!(
+            parse_location("lat=40.7"),
+            Err(ParseError::MissingParameter("lon"))
+        );
+        assert_eq!(
+            parse_location("foo=bar"),
+            Err(ParseError::UnknownParameter("foo".into()))
+        );
+        assert_eq!(
+            parse_location("zip=10001&lat=40.7&lon=-73.9"),
+            Err(ParseError::Ambiguous("zip=10001&lat=40.7&lon=-73.9".into()))
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(parse_location("  "), Err(ParseError::Empty));
+        assert_eq!(
+            parse_location("40.7"),
+            Err(ParseError::Ambiguous("40.7".into()))
+        );
+        assert_eq!(
+            parse_location("40.7,"),
+            Err(ParseError::Ambiguous("40.7,".into()))
+        );
+        assert_eq!(
+            parse_location("40.7,Astoria"),
+            Err(ParseError::Ambiguous("40.7,Astoria".into()))
+        );
+        assert_eq!(
+            parse_location("a,b,c,d"),
+            Err(ParseError::Ambiguous("a,b,c,d".into()))
+        );
+    }
+
+    #[test]
+    fn test_location_to_search() {
+        for s in ["11106", "10115,DE", "40.70000,-73.90000", "Astoria,OR,US"] {
+            let location = parse_location(s).unwrap();
+            assert_eq!(location_to_search(&location), s);
+            assert_eq!(parse_location(&location_to_search(&location)), Ok(location));
+        }
+    }
+
+    #[test]
+    fn test_location_label() {
+        let mut weather: WeatherData =
+            serde_json::from_str(include_str!("../tests/weather.json")).unwrap();
+        let coords = parse_location("40.760555,-73.929444").unwrap();
+        assert_eq!(location_label(&coords, None), "40.76,-73.93");
+        assert_eq!(location_label(&coords, Some(&weather)), "Astoria, US");
+        let zip = parse_location("11106").unwrap();
+        assert_eq!(location_label(&zip, Some(&weather)), "11106");
+        weather.sys.country = None;
+        assert_eq!(location_label(&coords, Some(&weather)), "Astoria");
+        weather.name = "".into();
+        assert_eq!(location_label(&coords, Some(&weather)), "40.76,-73.93");
+    }
+
+    proptest! {
+        #[test]
+        fn test_parse_any_zipcode(zip: u64) {
+            prop_assert_eq!(
+                parse_location(&zip.to_string()),
+                Ok(WeatherLocation::from_zipcode(zip))
+            );
+            prop_assert_eq!(
+                parse_location(&format!("zip={zip}")),
+                Ok(WeatherLocation::from_zipcode(zip))
+            );
+        }
+
+        #[test]
+        fn test_parse_any_lat_lon(lat in -89.99..89.99_f64, lon in -179.99..179.99_f64) {
+            let expected = lat_lon(lat, lon);
+            prop_assert_eq!(parse_location(&format!("{lat},{lon}")), Ok(expected.clone()));
+            prop_assert_eq!(parse_location(&format!("{lat}, {lon}")), Ok(expected.clone()));
+            prop_assert_eq!(parse_location(&format!("lat={lat}&lon={lon}")), Ok(expected));
+        }
+
+        #[test]
+        fn test_parse_never_panics(s in "\\PC*") {
+            let _ = parse_location(&s);
+            let _ = get_parameters(&s);
+        }
+
+        #[test]
+        fn test_parse_city_names(s in "[A-Za-z][A-Za-z ]{0,20}") {
+            prop_assert_eq!(
+                parse_location(&s),
+                Ok(WeatherLocation::from_city_name(s.trim()))
+            );
+        }
+    }
+}
@@ -0,0 +1,374 @@
+use async_trait::async_trait;
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+use stack_string::{format_sstr, StackString};
+use std::{cell::Cell, collections::HashMap, convert::TryFrom};
+use weather_util_rust::{
+    latitude::Latitude, longitude::Longitude, weather_api::WeatherLocation,
+    weather_forecast::WeatherForecast,
+};
+
+use crate::{
+    alerts::{alerts_location, WeatherAlert},
+    current::CurrentWeather,
+    entry::WeatherEntry,
+    error::WeatherError,
+    hourly::HourlyForecast,
+    location::parse_location,
+};
+
+/// Shortest search that is worth asking the geocoding api about
+pub const SUGGEST_MIN_CHARS: usize = 3;
+
+/// A place returned by the openweathermap geocoding api
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GeoLocation {
+    pub name: StackString,
+    pub lat: f64,
+    pub lon: f64,
+    #[serde(default)]
+    pub country: Option<StackString>,
+    #[serde(default)]
+    pub state: Option<StackString>,
+}
+
+impl GeoLocation {
+    /// Display name such as "Astoria, OR, US"
+    #[must_use]
+    pub fn label(&self) -> StackString {
+        let mut label = self.name.clone();
+        for part in self.state.iter().chain(self.country.iter()) {
+            label.push_str(", ");
+            label.push_str(part);
+        }
+        label
+    }
+
+    #[must_use]
+    pub fn to_location(&self) -> Option<WeatherLocation> {
+        let latitude = Latitude::try_from(self.lat).ok()?;
+        let longitude = Longitude::try_from(self.lon).ok()?;
+        Some(WeatherLocation::from_lat_lon(latitude, longitude))
+    }
+}
+
+/// Source of weather data, implemented over fetch in the web app, over
+/// `WeatherApi` in the desktop app and by `FixtureProvider` in tests.
+#[async_trait(?Send)]
+pub trait WeatherProvider {
+    async fn weather(&self, location: &WeatherLocation) -> Result<CurrentWeather, WeatherError>;
+    async fn forecast(&self, location: &WeatherLocation) -> Result<HourlyForecast, WeatherError>;
+    async fn geocode(&self, query: &str) -> Result<Vec<GeoLocation>, WeatherError>;
+    /// Alerts at a `LatLon` location from the One Call api
+    async fn alerts(&self, location: &WeatherLocation) -> Result<Vec<WeatherAlert>, WeatherError>;
+}
+
+/// Fetch current conditions, the forecast and any alerts for `location`,
+/// recording the first error (if any) on the returned entry.
+///
+/// Alerts are left out rather than failing the entry when they can't be
+/// fetched, as not every endpoint or api key gives access to One Call.
+pub async fn fetch_entry(
+    provider: &impl WeatherProvider,
+    location: &WeatherLocation,
+) -> WeatherEntry {
+    let weather = provider.weather(location).await;
+    let hourly = provider.forecast(location).await;
+    let error = match (&weather, &hourly) {
+        (Err(e), _) | (_, Err(e)) => Some(e.clone().for_location(location)),
+        _ => None,
+    };
+    let alerts = match &weather {
+        Ok(current) => provider
+            .alerts(&alerts_location(location, &current.weather))
+            .await
+            .unwrap_or_default(),
+        Err(_) => Vec::new(),
+    };
+    let (weather, clouds) = match weather {
+        Ok(current) => (Some(current.weather), current.clouds),
+        Err(_) => (None, None),
+    };
+    let hourly = hourly.ok();
+    let forecast: Option<WeatherForecast> = hourly.as_ref().map(Into::into);
+    WeatherEntry::new(weather, forecast)
+        .with_hourly(hourly)
+        .with_clouds(clouds)
+        .with_alerts(alerts)
+        .with_error(error)
+}
+
+/// Fetch several locations concurrently, e.g. for the favourites dashboard.
+pub async fn fetch_entries(
+    provider: &impl WeatherProvider,
+    locations: &[WeatherLocation],
+) -> Vec<(WeatherLocation, WeatherEntry)> {
+    join_all(
+        locations.iter().map(|location| async move {
+            (location.clone(), fetch_entry(provider, location).await)
+        }),
+    )
+    .await
+}
+
+/// Places matching a partly typed city name, for type-ahead suggestions.
+///
+/// Zipcodes, coordinates and anything shorter than `SUGGEST_MIN_CHARS` are
+/// answered with no suggestions and without a request, and places the api
+/// lists more than once under the same label are only shown once.
+///
+/// # Errors
+///
+/// Returns the provider's `WeatherError` if the geocoding request fails
+pub async fn suggest(
+    provider: &impl WeatherProvider,
+    draft: &str,
+) -> Result<Vec<GeoLocation>, WeatherError> {
+    let query = draft.trim();
+    if query.chars().count() < SUGGEST_MIN_CHARS
+        || !matches!(parse_location(query), Ok(WeatherLocation::CityName(_)))
+    {
+        return Ok(Vec::new());
+    }
+    let mut places = provider.geocode(query).await?;
+    let mut seen = Vec::new();
+    places.retain(|p| {
+        let label = p.label();
+        if seen.contains(&label) {
+            false
+        } else {
+            seen.push(label);
+            true
+        }
+    });
+    Ok(places)
+}
+
+/// In-memory provider answering from canned data, and with a 404 like the
+/// real api for anything it doesn't know.
+#[derive(Debug, Default)]
+pub struct FixtureProvider {
+    weather: HashMap<WeatherLocation, CurrentWeather>,
+    forecast: HashMap<WeatherLocation, HourlyForecast>,
+    places: Vec<GeoLocation>,
+    alerts: Vec<WeatherAlert>,
+    requests: Cell<usize>,
+}
+
+impl FixtureProvider {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn with_weather(mut self, location: WeatherLocation, weather: CurrentWeather) -> Self {
+        self.weather.insert(location, weather);
+        self
+    }
+
+    #[must_use]
+    pub fn with_forecast(mut self, location: WeatherLocation, forecast: HourlyForecast) -> Self {
+        self.forecast.insert(location, forecast);
+        self
+    }
+
+    #[must_use]
+    pub fn with_places(mut self, places: Vec<GeoLocation>) -> Self {
+        self.places = places;
+        self
+    }
+
+    /// Alerts given for every location
+    #[must_use]
+    pub fn with_alerts(mut self, alerts: Vec<WeatherAlert>) -> Self {
+        self.alerts = alerts;
+        self
+    }
+
+    /// Number of requests answered so far
+    #[must_use]
+    pub fn requests(&self) -> usize {
+        self.requests.get()
+    }
+
+    fn record(&self) {
+        self.requests.set(self.requests.get() + 1);
+    }
+}
+
+#[async_trait(?Send)]
+impl WeatherProvider for FixtureProvider {
+    async fn weather(&self, location: &WeatherLocation) -> Result<CurrentWeather, WeatherError> {
+        self.record();
+        self.weather
+            .get(location)
+            .cloned()
+            .ok_or(WeatherError::HttpStatus(404))
+    }
+
+    async fn forecast(&self, location: &WeatherLocation) -> Result<HourlyForecast, WeatherError> {
+        self.record();
+        self.forecast
+            .get(location)
+            .cloned()
+            .ok_or(WeatherError::HttpStatus(404))
+    }
+
+    async fn geocode(&self, query: &str) -> Result<Vec<GeoLocation>, WeatherError> {
+        self.record();
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return Err(WeatherError::UnknownLocation(format_sstr!("{query}")));
+        }
+        Ok(self
+            .places
+            .iter()
+            .filter(|p| p.name.to_lowercase().starts_with(&query))
+            .cloned()
+            .collect())
+    }
+
+    async fn alerts(&self, location: &WeatherLocation) -> Result<Vec<WeatherAlert>, WeatherError> {
+        self.record();
+        match location {
+            WeatherLocation::LatLon { .. } => Ok(self.alerts.clone()),
+            _ => Err(WeatherError::HttpStatus(400)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use time::macros::datetime;
+    use weather_util_rust::weather_api::WeatherLocation;
+
+    use crate::{
+        alerts::WeatherAlert,
+        current::{Clouds, CurrentWeather},
+        error::WeatherError,
+        hourly::HourlyForecast,
+        location::get_parameters,
+        provider::{
+            fetch_entries, fetch_entry, suggest, FixtureProvider, GeoLocation, WeatherProvider,
+        },
+    };
+
+    fn astoria() -> GeoLocation {
+        GeoLocation {
+            name: "Astoria".into(),
+            lat: 46.1879,
+            lon: -123.8313,
+            country: Some("US".into()),
+            state: Some("Oregon".into()),
+        }
+    }
+
+    fn wind_advisory() -> WeatherAlert {
+        WeatherAlert {
+            sender_name: "NWS Upton NY".into(),
+            event: "Wind Advisory".into(),
+            start: datetime!(2022-03-04 22:00 UTC),
+            end: datetime!(2022-03-05 10:00 UTC),
+            description: "West winds 20 to 30 mph.".into(),
+            tags: Vec::new(),
+        }
+    }
+
+    fn fixture_provider(location: &WeatherLocation) -> FixtureProvider {
+        let weather: CurrentWeather =
+            serde_json::from_str(include_str!("../tests/weather.json")).unwrap();
+        let forecast: HourlyForecast =
+            serde_json::from_str(include_str!("../tests/forecast.json")).unwrap();
+        FixtureProvider::new()
+            .with_weather(location.clone(), weather)
+            .with_forecast(location.clone(), forecast)
+            .with_places(vec![astoria()])
+            .with_alerts(vec![wind_advisory()])
+    }
+
+    #[test]
+    fn test_fetch_entry() {
+        let location = get_parameters("11106");
+        let provider = fixture_provider(&location);
+        let entry = block_on(fetch_entry(&provider, &location));
+        assert_eq!(entry.error, None);
+        assert!(entry.weather.is_some());
+        assert_eq!(entry.clouds, Some(Clouds { all: 75 }));
+        assert_eq!(
+            entry.forecast.as_ref().map(|f| f.list.len()),
+            entry.hourly.as_ref().map(|h| h.list.len())
+        );
+        assert_eq!(entry.alerts.len(), 1);
+        assert_eq!(provider.requests(), 3);
+    }
+
+    #[test]
+    fn test_fetch_entry_without_alerts() {
+        let location = get_parameters("11106");
+        let provider = FixtureProvider::new().with_weather(
+            location.clone(),
+            fixture_provider(&location).weather[&location].clone(),
+        );
+        let entry = block_on(fetch_entry(&provider, &location));
+        assert!(entry.weather.is_some());
+        assert!(entry.alerts.is_empty());
+        assert_eq!(provider.requests(), 3);
+    }
+
+    #[test]
+    fn test_fetch_entry_unknown_location() {
+        let provider = fixture_provider(&get_parameters("11106"));
+        let location = get_parameters("Nowhere");
+        let entry = block_on(fetch_entry(&provider, &location));
+        assert_eq!(
+            entry.error,
+            Some(WeatherError::UnknownLocation("Nowhere".into()))
+        );
+        assert!(entry.weather.is_none());
+        assert!(entry.forecast.is_none());
+        assert!(entry.alerts.is_empty());
+        assert_eq!(provider.requests(), 2);
+    }
+
+    #[test]
+    fn test_fetch_entries() {
+        let loc = get_parameters("11106");
+        let unknown = get_parameters("10001");
+        let provider = fixture_provider(&loc);
+        let entries = block_on(fetch_entries(&provider, &[loc.clone(), unknown.clone()]));
+        assert_eq!(provider.requests(), 5);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].0, loc);
+        assert!(entries[0].1.weather.is_some());
+        assert_eq!(entries[1].0, unknown);
+        assert!(entries[1].1.error.is_some());
+        assert!(block_on(fetch_entries(&provider, &[])).is_empty());
+    }
+
+    #[test]
+    fn test_geocode() {
+        let provider = fixture_provider(&get_parameters("11106"));
+        let places = block_on(provider.geocode("ast")).unwrap();
+        assert_eq!(places, vec![astoria()]);
+        assert_eq!(places[0].label(), "Astoria, Oregon, US");
+        assert!(places[0].to_location().is_some());
+        assert!(block_on(provider.geocode("paris")).unwrap().is_empty());
+        assert!(block_on(provider.geocode(" ")).is_err());
+    }
+
+    #[test]
+    fn test_suggest() {
+        let provider = FixtureProvider::new().with_places(vec![astoria(), astoria()]);
+        for draft in ["", "as", "11106", "40.7,-73.9", "zip=11106"] {
+            assert!(block_on(suggest(&provider, draft)).unwrap().is_empty());
+        }
+        assert_eq!(provider.requests(), 0);
+        assert_eq!(
+            block_on(suggest(&provider, " Ast ")).unwrap(),
+            vec![astoria()]
+        );
+        assert_eq!(provider.requests(), 1);
+    }
+}
@@ -0,0 +1,334 @@
+use anyhow::{format_err, Error};
+use stack_string::{format_sstr, StackString};
+use std::str::FromStr;
+use url::form_urlencoded::{self, Serializer};
+use weather_util_rust::weather_api::WeatherLocation;
+
+use crate::{
+    components::ForecastView,
+    location::{get_parameters, location_to_search, parse_location},
+    units::{PrecipitationUnit, SpeedUnit, TemperatureUnit, UnitSystem},
+};
+
+static APP_KEYS: [&str; 5] = ["location", "temperature", "precipitation", "speed", "view"];
+static INDEX_KEYS: [&str; 5] = ["location", "lat", "lon", "zip", "view"];
+static INDEX_LOCATION_KEYS: [&str; 3] = ["lat", "lon", "zip"];
+
+fn query_pairs(query: &str) -> form_urlencoded::Parse<'_> {
+    form_urlencoded::parse(query.trim_start_matches('?').as_bytes())
+}
+
+/// The parameters of `query` other than `keys`, to append a route to
+fn other_params(query: &str, keys: &[&str]) -> Serializer<'static, String> {
+    let mut serializer = Serializer::new(String::new());
+    for (key, value) in query_pairs(query) {
+        if !keys.contains(&key.as_ref()) {
+            serializer.append_pair(&key, &value);
+        }
+    }
+    serializer
+}
+
+fn finish(mut serializer: Serializer<'static, String>) -> StackString {
+    let query = serializer.finish();
+    if query.is_empty() {
+        StackString::new()
+    } else {
+        format_sstr!("?{query}")
+    }
+}
+
+/// The part of the web app's state kept in its query string, e.g.
+/// `?location=11106&temperature=celsius&view=hourly`, so a view can be
+/// bookmarked and shared.  Missing or invalid parameters are `None` and leave
+/// the app's own state alone.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AppRoute {
+    pub location: Option<StackString>,
+    pub temperature: Option<TemperatureUnit>,
+    pub precipitation: Option<PrecipitationUnit>,
+    pub speed: Option<SpeedUnit>,
+    pub view: Option<ForecastView>,
+}
+
+impl AppRoute {
+    #[must_use]
+    pub fn new(location: &WeatherLocation, units: UnitSystem, view: ForecastView) -> Self {
+        Self {
+            location: Some(location_to_search(location)),
+            temperature: Some(units.temperature),
+            precipitation: Some(units.precipitation),
+            speed: Some(units.speed),
+            view: Some(view),
+        }
+    }
+
+    /// Parse `window.location.search`, with or without the leading `?`
+    #[must_use]
+    pub fn from_query(query: &str) -> Self {
+        let mut route = Self::default();
+        for (key, value) in query_pairs(query) {
+            match key.as_ref() {
+                "location" if !value.trim().is_empty() => {
+                    route.location = Some(value.trim().into());
+                }
+                "temperature" => route.temperature = value.parse().ok(),
+                "precipitation" => route.precipitation = value.parse().ok(),
+                "speed" => route.speed = value.parse().ok(),
+                "view" => route.view = value.parse().ok(),
+                _ => {}
+            }
+        }
+        route
+    }
+
+    #[must_use]
+    pub fn get_location(&self) -> Option<WeatherLocation> {
+        self.location.as_ref().map(|s| get_parameters(s))
+    }
+
+    /// `units` with the units given in the route replacing it
+    #[must_use]
+    pub fn get_units(&self, units: UnitSystem) -> UnitSystem {
+        UnitSystem {
+            temperature: self.temperature.unwrap_or(units.temperature),
+            precipitation: self.precipitation.unwrap_or(units.precipitation),
+            speed: self.speed.unwrap_or(units.speed),
+        }
+    }
+
+    /// Query string for this route, keeping any other parameters of `query`
+    /// such as `api_endpoint`.
+    #[must_use]
+    pub fn to_query(&self, query: &str) -> StackString {
+        let mut serializer = other_params(query, &APP_KEYS);
+        if let Some(location) = &self.location {
+            serializer.append_pair("location", location);
+        }
+        if let Some(temperature) = self.temperature {
+            serializer.append_pair("temperature", temperature.to_str());
+        }
+        if let Some(precipitation) = self.precipitation {
+            serializer.append_pair("precipitation", precipitation.to_str());
+        }
+        if let Some(speed) = self.speed {
+            serializer.append_pair("speed", speed.to_str());
+        }
+        if let Some(view) = self.view {
+            serializer.append_pair("view", &view.to_str().to_ascii_lowercase());
+        }
+        finish(serializer)
+    }
+}
+
+/// Page weather-index shows in its frame
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum IndexView {
+    Text,
+    #[default]
+    Plot,
+    Wasm,
+}
+
+impl IndexView {
+    pub const ALL: [Self; 3] = [Self::Text, Self::Plot, Self::Wasm];
+
+    #[must_use]
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Plot => "plot",
+            Self::Wasm => "wasm",
+        }
+    }
+
+    /// Path of the page relative to the site root
+    #[must_use]
+    pub fn url_path(self) -> &'static str {
+        match self {
+            Self::Text => "weather/index.html",
+            Self::Plot => "weather/plot.html",
+            Self::Wasm => "wasm_weather/index.html",
+        }
+    }
+}
+
+impl FromStr for IndexView {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|v| v.to_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format_err!("Invalid view {s}"))
+    }
+}
+
+/// weather-index's query string.  The location is given as a search string
+/// (`?location=New%20York`), as coordinates (`?lat=40.7&lon=-73.9`) or as a
+/// zipcode (`?zip=10001`), and `view` picks the page shown.  `location` wins
+/// if several are given, and invalid values are `None`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IndexRoute {
+    pub location: Option<WeatherLocation>,
+    pub view: Option<IndexView>,
+}
+
+impl IndexRoute {
+    #[must_use]
+    pub fn new(location: &WeatherLocation, view: IndexView) -> Self {
+        Self {
+            location: Some(location.clone()),
+            view: Some(view),
+        }
+    }
+
+    #[must_use]
+    pub fn from_query(query: &str) -> Self {
+        let mut search = None;
+        let mut view = None;
+        let mut location_params = Serializer::new(String::new());
+        for (key, value) in query_pairs(query) {
+            match key.as_ref() {
+                "location" if !value.trim().is_empty() => search = Some(get_parameters(&value)),
+                "view" => view = value.parse().ok(),
+                key if INDEX_LOCATION_KEYS.contains(&key) => {
+                    location_params.append_pair(key, &value);
+                }
+                _ => {}
+            }
+        }
+        let location = search.or_else(|| parse_location(&location_params.finish()).ok());
+        Self { location, view }
+    }
+
+    /// Query string for this route, keeping any other parameters of `query`.
+    /// Zipcodes and coordinates are written as `zip` and `lat`/`lon`.
+    #[must_use]
+    pub fn to_query(&self, query: &str) -> StackString {
+        let mut serializer = other_params(query, &INDEX_KEYS);
+        match &self.location {
+            Some(WeatherLocation::ZipCode {
+                zipcode,
+                country_code: None,
+            }) => {
+                serializer.append_pair("zip", &format_sstr!("{zipcode}"));
+            }
+            Some(WeatherLocation::LatLon {
+                latitude,
+                longitude,
+            }) => {
+                serializer.append_pair("lat", &format_sstr!("{latitude}"));
+                serializer.append_pair("lon", &format_sstr!("{longitude}"));
+            }
+            Some(location) => {
+                serializer.append_pair("location", &location_to_search(location));
+            }
+            None => {}
+        }
+        if let Some(view) = self.view {
+            serializer.append_pair("view", view.to_str());
+        }
+        finish(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use weather_util_rust::weather_api::WeatherLocation;
+
+    use crate::{
+        components::ForecastView,
+        location::get_parameters,
+        route::{AppRoute, IndexRoute, IndexView},
+        units::{PrecipitationUnit, SpeedUnit, TemperatureUnit, UnitSystem},
+    };
+
+    #[test]
+    fn test_from_query() {
+        let route = AppRoute::from_query(
+            "?location=Astoria%2COR&temperature=celsius&speed=bogus&view=Hourly",
+        );
+        assert_eq!(route.location, Some("Astoria,OR".into()));
+        assert_eq!(
+            route.get_location(),
+            Some(WeatherLocation::from_city_name("Astoria,OR"))
+        );
+        assert_eq!(route.view, Some(ForecastView::Hourly));
+        assert_eq!(
+            route.get_units(UnitSystem::default()),
+            UnitSystem {
+                temperature: TemperatureUnit::Celsius,
+                ..UnitSystem::default()
+            }
+        );
+        assert_eq!(AppRoute::from_query(""), AppRoute::default());
+        assert_eq!(AppRoute::from_query("location=%20").location, None);
+    }
+
+    #[test]
+    fn test_to_query() {
+        let units = UnitSystem {
+            temperature: TemperatureUnit::Kelvin,
+            precipitation: PrecipitationUnit::Millimeters,
+            speed: SpeedUnit::MetersPerSecond,
+        };
+        let location = WeatherLocation::from_zipcode(11106);
+        let route = AppRoute::new(&location, units, ForecastView::Chart);
+        let query = route.to_query("?api_endpoint=http%3A%2F%2Flocalhost%2F&view=daily");
+        assert_eq!(
+            query,
+            "?api_endpoint=http%3A%2F%2Flocalhost%2F&location=11106&temperature=kelvin\
+             &precipitation=millimeters&speed=meterspersecond&view=chart"
+        );
+        let parsed = AppRoute::from_query(&query);
+        assert_eq!(parsed, route);
+        assert_eq!(parsed.get_location(), Some(location));
+        assert_eq!(AppRoute::default().to_query(""), "");
+    }
+
+    #[test]
+    fn test_index_route_from_query() {
+        let new_york = WeatherLocation::from_city_name("New York");
+        let route = IndexRoute::from_query("?view=text&location=New%20York");
+        assert_eq!(route.location.as_ref(), Some(&new_york));
+        assert_eq!(route.view, Some(IndexView::Text));
+        assert_eq!(
+            IndexRoute::from_query("location=New+York&zip=10001").location,
+            Some(new_york)
+        );
+        assert_eq!(
+            IndexRoute::from_query("lon=-73.9&lat=40.7&view=Wasm"),
+            IndexRoute {
+                location: Some(get_parameters("40.7,-73.9")),
+                view: Some(IndexView::Wasm),
+            }
+        );
+        assert_eq!(
+            IndexRoute::from_query("zip=10001").location,
+            Some(WeatherLocation::from_zipcode(10001))
+        );
+        assert_eq!(
+            IndexRoute::from_query("lat=40.7&view=map"),
+            IndexRoute::default()
+        );
+        assert_eq!(IndexView::Plot.url_path(), "weather/plot.html");
+    }
+
+    #[test]
+    fn test_index_route_to_query() {
+        for (search, query) in [
+            ("10001", "?api_endpoint=x&zip=10001&view=plot"),
+            (
+                "40.7,-73.9",
+                "?api_endpoint=x&lat=40.70000&lon=-73.90000&view=plot",
+            ),
+            ("New York", "?api_endpoint=x&location=New+York&view=plot"),
+            ("10115,DE", "?api_endpoint=x&location=10115%2CDE&view=plot"),
+        ] {
+            let route = IndexRoute::new(&get_parameters(search), IndexView::Plot);
+            let written = route.to_query("?zip=1&api_endpoint=x&view=text");
+            assert_eq!(written, query);
+            assert_eq!(IndexRoute::from_query(&written), route);
+        }
+    }
+}
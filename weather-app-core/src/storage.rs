@@ -0,0 +1,327 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use stack_string::StackString;
+use weather_util_rust::{weather_api::WeatherLocation, weather_data::WeatherData};
+
+use crate::{
+    alerts::MAX_DISMISSED_ALERTS,
+    entry::WeatherEntry,
+    error::StorageError,
+    location::{get_parameters, location_label, location_to_search},
+};
+
+/// Bump when the layout of `StoredState` changes, adding a step to `migrate`.
+pub const STORAGE_VERSION: u64 = 2;
+pub const MAX_HISTORY: usize = 50;
+
+/// Everything the web app keeps in localStorage between visits.
+///
+/// Locations are stored as search strings, since `WeatherLocation` does not
+/// implement serde; `location_to_search` turns one back into a search string.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct StoredState {
+    #[serde(default)]
+    pub version: u64,
+    #[serde(default)]
+    pub history: Vec<HistoryEntry>,
+    #[serde(default)]
+    pub favourites: Vec<StackString>,
+    #[serde(default)]
+    pub last_location: Option<StackString>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cache: Vec<(StackString, WeatherEntry)>,
+    /// Ids of the weather alerts the user has dismissed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dismissed_alerts: Vec<StackString>,
+}
+
+/// A searched location: the exact search string and the name shown for it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub search: StackString,
+    pub label: StackString,
+}
+
+impl HistoryEntry {
+    /// Labelled with the search string until there is weather data to name it
+    pub fn new(search: impl Into<StackString>) -> Self {
+        let search = search.into();
+        let label = location_label(&get_parameters(&search), None);
+        Self { search, label }
+    }
+
+    #[must_use]
+    pub fn from_location(location: &WeatherLocation, weather: Option<&WeatherData>) -> Self {
+        Self {
+            search: location_to_search(location),
+            label: location_label(location, weather),
+        }
+    }
+
+    #[must_use]
+    pub fn location(&self) -> WeatherLocation {
+        get_parameters(&self.search)
+    }
+
+    /// Label from the weather fetched for this entry's location, or `None`
+    /// if it is for a different location or already has a name.
+    #[must_use]
+    pub fn relabel(&self, location: &WeatherLocation, weather: &WeatherData) -> Option<Self> {
+        if self.location() != *location || self.label != location_label(location, None) {
+            return None;
+        }
+        let label = location_label(location, Some(weather));
+        (label != self.label).then(|| Self {
+            search: self.search.clone(),
+            label,
+        })
+    }
+}
+
+impl StoredState {
+    /// # Errors
+    ///
+    /// Returns `StorageError::Corrupt` if the data is not valid json for any
+    /// known version, and `StorageError::UnsupportedVersion` if it was written
+    /// by a newer version of the app.
+    pub fn from_json(s: &str) -> Result<Self, StorageError> {
+        let value: Value = serde_json::from_str(s).map_err(StorageError::corrupt)?;
+        let mut value = migrate(value)?;
+        // The cache is only an optimisation, so entries that no longer parse
+        // are dropped rather than losing the history along with them
+        let cache = value.as_object_mut().and_then(|obj| obj.remove("cache"));
+        let mut state: Self = serde_json::from_value(value).map_err(StorageError::corrupt)?;
+        state.cache = match cache {
+            Some(Value::Array(entries)) => entries
+                .into_iter()
+                .filter_map(|entry| serde_json::from_value(entry).ok())
+                .collect(),
+            _ => Vec::new(),
+        };
+        state.normalize();
+        Ok(state)
+    }
+
+    /// # Errors
+    ///
+    /// Returns `StorageError::Corrupt` if serialization fails
+    pub fn to_json(&self) -> Result<String, StorageError> {
+        let state = Self {
+            version: STORAGE_VERSION,
+            ..self.clone()
+        };
+        serde_json::to_string(&state).map_err(StorageError::corrupt)
+    }
+
+    /// Drop empty and duplicate entries, keeping the most recent of each, at
+    /// most `MAX_HISTORY` history entries and `MAX_DISMISSED_ALERTS` dismissed
+    /// alerts.
+    pub fn normalize(&mut self) {
+        fn dedup_keep_last<T>(v: &mut Vec<T>, key: impl Fn(&T) -> &str) {
+            let mut seen: Vec<T> = Vec::new();
+            for s in v.drain(..).rev() {
+                if !key(&s).trim().is_empty() && !seen.iter().any(|x| key(x) == key(&s)) {
+                    seen.push(s);
+                }
+            }
+            seen.reverse();
+            *v = seen;
+        }
+        dedup_keep_last(&mut self.history, |h| &h.search);
+        dedup_keep_last(&mut self.favourites, StackString::as_str);
+        dedup_keep_last(&mut self.dismissed_alerts, StackString::as_str);
+        if self.history.len() > MAX_HISTORY {
+            self.history.drain(..self.history.len() - MAX_HISTORY);
+        }
+        if self.dismissed_alerts.len() > MAX_DISMISSED_ALERTS {
+            self.dismissed_alerts
+                .drain(..self.dismissed_alerts.len() - MAX_DISMISSED_ALERTS);
+        }
+        self.version = STORAGE_VERSION;
+    }
+}
+
+/// Upgrade stored json one version at a time until it matches `StoredState`.
+fn migrate(mut value: Value) -> Result<Value, StorageError> {
+    let mut version = match &value {
+        // weather-index's `set_history` wrote a bare array of search strings
+        Value::Array(_) => 0,
+        Value::Object(obj) => obj
+            .get("version")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| StorageError::Corrupt("missing version".into()))?,
+        _ => return Err(StorageError::Corrupt("expected an object".into())),
+    };
+    if version > STORAGE_VERSION {
+        return Err(StorageError::UnsupportedVersion(version, STORAGE_VERSION));
+    }
+    while version < STORAGE_VERSION {
+        value = match version {
+            0 => json!({"version": 1, "history": value}),
+            // history entries gained a label alongside the search string
+            1 => {
+                if let Some(Value::Array(history)) = value.get_mut("history") {
+                    for entry in history.iter_mut() {
+                        if let Value::String(search) = entry {
+                            *entry = json!(HistoryEntry::new(search.as_str()));
+                        }
+                    }
+                }
+                value["version"] = json!(2);
+                value
+            }
+            _ => unreachable!("no migration from version {version}"),
+        };
+        version += 1;
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use weather_util_rust::weather_data::WeatherData;
+
+    use crate::{
+        alerts::MAX_DISMISSED_ALERTS,
+        entry::WeatherEntry,
+        error::StorageError,
+        location::parse_location,
+        storage::{HistoryEntry, StoredState, MAX_HISTORY, STORAGE_VERSION},
+    };
+
+    fn searches(history: &[HistoryEntry]) -> Vec<&str> {
+        history.iter().map(|h| h.search.as_str()).collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let weather: WeatherData =
+            serde_json::from_str(include_str!("../tests/weather.json")).unwrap();
+        let coords = parse_location("40.76056,-73.92944").unwrap();
+        let state = StoredState {
+            version: 0,
+            history: vec![
+                HistoryEntry::new("11106"),
+                HistoryEntry::from_location(&coords, Some(&weather)),
+            ],
+            favourites: vec!["10001".into()],
+            last_location: Some("Astoria".into()),
+            cache: vec![("Astoria".into(), WeatherEntry::new(Some(weather), None))],
+            dismissed_alerts: vec!["NWS Upton NY:Wind Advisory:1646431200".into()],
+        };
+        let s = state.to_json().unwrap();
+        let loaded = StoredState::from_json(&s).unwrap();
+        assert_eq!(loaded.version, STORAGE_VERSION);
+        assert_eq!(loaded.history, state.history);
+        assert_eq!(loaded.favourites, state.favourites);
+        assert_eq!(loaded.last_location, state.last_location);
+        assert_eq!(loaded.dismissed_alerts, state.dismissed_alerts);
+        assert_eq!(loaded.cache, state.cache);
+    }
+
+    #[test]
+    fn test_migrate_legacy_history() {
+        let loaded = StoredState::from_json(r#"["11106","zip=10001","11106"]"#).unwrap();
+        assert_eq!(loaded.version, STORAGE_VERSION);
+        assert_eq!(searches(&loaded.history), vec!["zip=10001", "11106"]);
+        assert!(loaded.favourites.is_empty());
+        assert_eq!(loaded.last_location, None);
+    }
+
+    #[test]
+    fn test_corrupt_cache_entry() {
+        let loaded = StoredState::from_json(
+            r#"{"version":1,"history":["11106"],"cache":[["11106",{"weather":{"bad":1}}],"junk"]}"#,
+        )
+        .unwrap();
+        assert_eq!(searches(&loaded.history), vec!["11106"]);
+        assert!(loaded.cache.is_empty());
+    }
+
+    #[test]
+    fn test_migrate_v1_history() {
+        let loaded = StoredState::from_json(
+            r#"{"version":1,"history":["11106","40.760555,-73.929444"],"favourites":["11106"]}"#,
+        )
+        .unwrap();
+        assert_eq!(loaded.version, STORAGE_VERSION);
+        assert_eq!(
+            loaded.history,
+            vec![
+                HistoryEntry {
+                    search: "11106".into(),
+                    label: "11106".into()
+                },
+                HistoryEntry {
+                    search: "40.760555,-73.929444".into(),
+                    label: "40.76,-73.93".into()
+                },
+            ]
+        );
+        assert_eq!(loaded.favourites, vec!["11106"]);
+    }
+
+    #[test]
+    fn test_relabel() {
+        let weather: WeatherData =
+            serde_json::from_str(include_str!("../tests/weather.json")).unwrap();
+        let entry = HistoryEntry::new("40.760555,-73.929444");
+        let location = entry.location();
+        let relabelled = entry.relabel(&location, &weather).unwrap();
+        assert_eq!(relabelled.search, entry.search);
+        assert_eq!(relabelled.label, "Astoria, US");
+        assert_eq!(relabelled.relabel(&location, &weather), None);
+        let other = parse_location("11106").unwrap();
+        assert_eq!(entry.relabel(&other, &weather), None);
+        assert_eq!(HistoryEntry::new("11106").relabel(&other, &weather), None);
+        let picked = HistoryEntry {
+            label: "Astoria, New York, US".into(),
+            ..entry.clone()
+        };
+        assert_eq!(picked.relabel(&location, &weather), None);
+    }
+
+    #[test]
+    fn test_corrupt_data() {
+        assert!(matches!(
+            StoredState::from_json("{not json"),
+            Err(StorageError::Corrupt(_))
+        ));
+        assert!(matches!(
+            StoredState::from_json(r#"{"history":["11106"]}"#),
+            Err(StorageError::Corrupt(_))
+        ));
+        assert!(matches!(
+            StoredState::from_json(r#"{"version":1,"history":"11106"}"#),
+            Err(StorageError::Corrupt(_))
+        ));
+        assert!(matches!(
+            StoredState::from_json("42"),
+            Err(StorageError::Corrupt(_))
+        ));
+        assert_eq!(
+            StoredState::from_json(r#"{"version":99}"#),
+            Err(StorageError::UnsupportedVersion(99, STORAGE_VERSION))
+        );
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut state = StoredState {
+            history: (0..MAX_HISTORY + 10)
+                .map(|i| HistoryEntry::new(i.to_string()))
+                .chain([HistoryEntry::new(""), HistoryEntry::new("5")])
+                .collect(),
+            dismissed_alerts: (0..MAX_DISMISSED_ALERTS + 1)
+                .map(|i| i.to_string().into())
+                .collect(),
+            ..StoredState::default()
+        };
+        state.normalize();
+        assert_eq!(state.dismissed_alerts.len(), MAX_DISMISSED_ALERTS);
+        assert_eq!(state.dismissed_alerts[0], "1");
+        assert_eq!(state.history.len(), MAX_HISTORY);
+        assert_eq!(state.history.last().map(|h| h.search.as_str()), Some("5"));
+        assert_eq!(state.history.iter().filter(|h| h.search == "5").count(), 1);
+    }
+}
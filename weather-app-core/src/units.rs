@@ -0,0 +1,229 @@
+use anyhow::{format_err, Error};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use weather_util_rust::{precipitation::Precipitation, speed::Speed, temperature::Temperature};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemperatureUnit {
+    Celsius,
+    #[default]
+    Fahrenheit,
+    Kelvin,
+}
+
+impl TemperatureUnit {
+    pub const ALL: [Self; 3] = [Self::Celsius, Self::Fahrenheit, Self::Kelvin];
+
+    #[must_use]
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Celsius => "celsius",
+            Self::Fahrenheit => "fahrenheit",
+            Self::Kelvin => "kelvin",
+        }
+    }
+
+    #[must_use]
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Self::Celsius => "°C",
+            Self::Fahrenheit => "°F",
+            Self::Kelvin => "K",
+        }
+    }
+
+    #[must_use]
+    pub fn convert(self, t: Temperature) -> f64 {
+        match self {
+            Self::Celsius => t.celcius(),
+            Self::Fahrenheit => t.fahrenheit(),
+            Self::Kelvin => t.kelvin(),
+        }
+    }
+}
+
+impl FromStr for TemperatureUnit {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|u| u.to_str() == s)
+            .ok_or_else(|| format_err!("Invalid temperature unit {s}"))
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrecipitationUnit {
+    Millimeters,
+    #[default]
+    Inches,
+}
+
+impl PrecipitationUnit {
+    pub const ALL: [Self; 2] = [Self::Millimeters, Self::Inches];
+
+    #[must_use]
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Millimeters => "millimeters",
+            Self::Inches => "inches",
+        }
+    }
+
+    #[must_use]
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Self::Millimeters => "mm",
+            Self::Inches => "\"",
+        }
+    }
+
+    #[must_use]
+    pub fn convert(self, p: Precipitation) -> f64 {
+        match self {
+            Self::Millimeters => p.millimeters(),
+            Self::Inches => p.inches(),
+        }
+    }
+}
+
+impl FromStr for PrecipitationUnit {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|u| u.to_str() == s)
+            .ok_or_else(|| format_err!("Invalid precipitation unit {s}"))
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpeedUnit {
+    MetersPerSecond,
+    #[default]
+    MilesPerHour,
+    KilometersPerHour,
+}
+
+impl SpeedUnit {
+    pub const ALL: [Self; 3] = [
+        Self::MetersPerSecond,
+        Self::MilesPerHour,
+        Self::KilometersPerHour,
+    ];
+
+    #[must_use]
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::MetersPerSecond => "meterspersecond",
+            Self::MilesPerHour => "milesperhour",
+            Self::KilometersPerHour => "kilometersperhour",
+        }
+    }
+
+    #[must_use]
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Self::MetersPerSecond => "m/s",
+            Self::MilesPerHour => "mph",
+            Self::KilometersPerHour => "km/h",
+        }
+    }
+
+    #[must_use]
+    pub fn convert(self, s: Speed) -> f64 {
+        match self {
+            Self::MetersPerSecond => s.mps(),
+            Self::MilesPerHour => s.mph(),
+            Self::KilometersPerHour => s.mps() * 3.6,
+        }
+    }
+}
+
+impl FromStr for SpeedUnit {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|u| u.to_str() == s)
+            .ok_or_else(|| format_err!("Invalid speed unit {s}"))
+    }
+}
+
+/// Units used to display temperature, precipitation and wind speed
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnitSystem {
+    #[serde(default)]
+    pub temperature: TemperatureUnit,
+    #[serde(default)]
+    pub precipitation: PrecipitationUnit,
+    #[serde(default)]
+    pub speed: SpeedUnit,
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Error;
+    use weather_util_rust::{precipitation::Precipitation, speed::Speed, temperature::Temperature};
+
+    use crate::units::{PrecipitationUnit, SpeedUnit, TemperatureUnit, UnitSystem};
+
+    #[test]
+    fn test_temperature_unit() -> Result<(), Error> {
+        let t = Temperature::from_celcius(10.0)?;
+        assert!((TemperatureUnit::Celsius.convert(t) - 10.0).abs() < 1e-6);
+        assert!((TemperatureUnit::Fahrenheit.convert(t) - 50.0).abs() < 1e-6);
+        assert!((TemperatureUnit::Kelvin.convert(t) - 283.15).abs() < 1e-6);
+        for u in TemperatureUnit::ALL {
+            assert_eq!(u.to_str().parse::<TemperatureUnit>()?, u);
+        }
+        assert!("rankine".parse::<TemperatureUnit>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_precipitation_unit() -> Result<(), Error> {
+        let p = Precipitation::from_inches(1.0)?;
+        assert!((PrecipitationUnit::Millimeters.convert(p) - 25.4).abs() < 1e-6);
+        assert!((PrecipitationUnit::Inches.convert(p) - 1.0).abs() < 1e-6);
+        for u in PrecipitationUnit::ALL {
+            assert_eq!(u.to_str().parse::<PrecipitationUnit>()?, u);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_speed_unit() -> Result<(), Error> {
+        let s = Speed::from_mps(10.0)?;
+        assert!((SpeedUnit::MetersPerSecond.convert(s) - 10.0).abs() < 1e-6);
+        assert!((SpeedUnit::KilometersPerHour.convert(s) - 36.0).abs() < 1e-6);
+        assert!((SpeedUnit::MilesPerHour.convert(s) - 22.369_362_92).abs() < 1e-6);
+        for u in SpeedUnit::ALL {
+            assert_eq!(u.to_str().parse::<SpeedUnit>()?, u);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_unit_system_serde() -> Result<(), Error> {
+        let units = UnitSystem {
+            temperature: TemperatureUnit::Celsius,
+            precipitation: PrecipitationUnit::Millimeters,
+            speed: SpeedUnit::KilometersPerHour,
+        };
+        let s = serde_json::to_string(&units)?;
+        assert_eq!(
+            s,
+            r#"{"temperature":"celsius","precipitation":"millimeters","speed":"kilometersperhour"}"#
+        );
+        let units2: UnitSystem = serde_json::from_str(&s)?;
+        assert_eq!(units, units2);
+        let units3: UnitSystem = serde_json::from_str(r#"{"temperature":"kelvin"}"#)?;
+        assert_eq!(units3.temperature, TemperatureUnit::Kelvin);
+        assert_eq!(units3.speed, SpeedUnit::default());
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+use dioxus::{
+    prelude::{dioxus_elements, format_args_f, rsx, LazyNodes, NodeFactory, VNode},
+    ssr::SsrRenderer,
+};
+use std::{env, fs, path::Path};
+use time::UtcOffset;
+use weather_util_rust::{weather_data::WeatherData, weather_forecast::WeatherForecast};
+
+use weather_app_core::{
+    alerts::{alert_banner, OneCallAlerts},
+    chart::forecast_chart,
+    compare::{compare_chart, DailySeries},
+    components::{
+        country_data, country_info, hourly_weather, page_layout, weather_card, weather_details,
+        week_weather, ForecastView,
+    },
+    current::{Clouds, CurrentWeather},
+    entry::WeatherEntry,
+    hourly::HourlyForecast,
+    units::{PrecipitationUnit, SpeedUnit, TemperatureUnit, UnitSystem},
+};
+
+fn weather() -> WeatherData {
+    serde_json::from_str(include_str!("weather.json")).unwrap()
+}
+
+fn clouds() -> Option<Clouds> {
+    serde_json::from_str::<CurrentWeather>(include_str!("weather.json"))
+        .unwrap()
+        .clouds
+}
+
+fn forecast() -> WeatherForecast {
+    serde_json::from_str(include_str!("forecast.json")).unwrap()
+}
+
+fn hourly() -> HourlyForecast {
+    serde_json::from_str(include_str!("forecast.json")).unwrap()
+}
+
+fn entry() -> WeatherEntry {
+    WeatherEntry::new(Some(weather()), Some(forecast())).with_clouds(clouds())
+}
+
+fn view_tabs<'a>() -> Vec<LazyNodes<'a, 'a>> {
+    ForecastView::ALL
+        .iter()
+        .map(|view| {
+            let label = view.to_str();
+            rsx!(button { "{label}" })
+        })
+        .collect()
+}
+
+fn metric() -> UnitSystem {
+    UnitSystem {
+        temperature: TemperatureUnit::Celsius,
+        precipitation: PrecipitationUnit::Millimeters,
+        speed: SpeedUnit::KilometersPerHour,
+    }
+}
+
+/// `SsrRenderer::render_lazy` ties the renderer's lifetime to the nodes, so
+/// the renderer is created inline for each snapshot.
+macro_rules! render {
+    ($nodes:expr) => {
+        SsrRenderer::new(|c| c.newline(true).indent(true)).render_lazy($nodes) + "\n"
+    };
+}
+
+/// Compare against `tests/snapshots/{name}.html`; run with
+/// `UPDATE_SNAPSHOTS=1` to write new snapshots after an intended change.
+fn assert_snapshot(name: &str, html: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(format!("{name}.html"));
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::write(&path, html).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!("missing snapshot {path:?}, run with UPDATE_SNAPSHOTS=1 to create it")
+    });
+    assert!(
+        html == expected,
+        "snapshot {name} differs, run with UPDATE_SNAPSHOTS=1 to accept\n--- expected\n{expected}\n--- actual\n{html}"
+    );
+}
+
+#[test]
+fn test_country_info() {
+    let weather = weather();
+    assert_snapshot(
+        "country_info",
+        &render!(country_info(&weather, UnitSystem::default())),
+    );
+    assert_snapshot(
+        "country_info_metric",
+        &render!(country_info(&weather, metric())),
+    );
+}
+
+#[test]
+fn test_country_data() {
+    let weather = weather();
+    assert_snapshot(
+        "country_data",
+        &render!(country_data(&weather, UnitSystem::default())),
+    );
+    assert_snapshot(
+        "country_data_metric",
+        &render!(country_data(&weather, metric())),
+    );
+}
+
+#[test]
+fn test_weather_details() {
+    let weather = weather();
+    assert_snapshot(
+        "weather_details",
+        &render!(weather_details(&weather, clouds(), UnitSystem::default())),
+    );
+    assert_snapshot(
+        "weather_details_metric",
+        &render!(weather_details(&weather, clouds(), metric())),
+    );
+}
+
+#[test]
+fn test_week_weather() {
+    let forecast = forecast();
+    assert_snapshot(
+        "week_weather",
+        &render!(week_weather(&forecast, UnitSystem::default())),
+    );
+    assert_snapshot(
+        "week_weather_metric",
+        &render!(week_weather(&forecast, metric())),
+    );
+}
+
+#[test]
+fn test_hourly_weather() {
+    let hourly = hourly();
+    assert_snapshot(
+        "hourly_weather",
+        &render!(hourly_weather(hourly, UnitSystem::default())),
+    );
+}
+
+#[test]
+fn test_forecast_chart() {
+    let forecast = forecast();
+    assert_snapshot(
+        "forecast_chart",
+        &render!(forecast_chart(&forecast, UnitSystem::default())),
+    );
+}
+
+#[test]
+fn test_compare_chart() {
+    let forecast = forecast();
+    let astoria = DailySeries::new("Astoria", &forecast, metric());
+    let mut warmer = DailySeries::new("Elsewhere", &forecast, metric());
+    warmer.days.remove(0);
+    for day in &mut warmer.days {
+        day.high += 2.0;
+        day.precip += 1.0;
+    }
+    assert_snapshot(
+        "compare_chart",
+        &render!(compare_chart(vec![astoria, warmer], metric())),
+    );
+}
+
+/// The weather card as both apps lay it out, daily forecast tab selected
+#[test]
+fn test_weather_card() {
+    let entry = entry();
+    let html = render!(weather_card(
+        Some(&entry),
+        UnitSystem::default(),
+        ForecastView::Daily,
+        false,
+        view_tabs()
+    ));
+    assert_snapshot("weather_card", &html);
+}
+
+#[test]
+fn test_weather_card_hourly() {
+    let entry = entry().with_hourly(Some(hourly()));
+    let html = render!(weather_card(
+        Some(&entry),
+        metric(),
+        ForecastView::Hourly,
+        true,
+        view_tabs()
+    ));
+    assert_snapshot("weather_card_hourly", &html);
+}
+
+#[test]
+fn test_weather_card_empty() {
+    let units = UnitSystem::default();
+    let html = render!(weather_card(
+        None,
+        units,
+        ForecastView::Daily,
+        true,
+        Vec::new()
+    ));
+    assert_snapshot("weather_card_loading", &html);
+
+    let failed = WeatherEntry::new(None, None);
+    let html = render!(weather_card(
+        Some(&failed),
+        units,
+        ForecastView::Daily,
+        false,
+        Vec::new()
+    ));
+    assert_snapshot("weather_card_no_data", &html);
+}
+
+#[test]
+fn test_page_layout() {
+    let html = render!(page_layout(
+        rsx!(input {
+            placeholder: "search..."
+        }),
+        vec![rsx!(div { "notice" })],
+        rsx!(div { "card" }),
+        vec![rsx!(details { "About" })]
+    ));
+    assert_snapshot("page_layout", &html);
+}
+
+#[test]
+fn test_alert_banner() {
+    let json = r#"{"alerts": [{
+        "sender_name": "NWS Upton NY",
+        "event": "Flood Warning",
+        "start": 1646431200,
+        "end": 1646517600,
+        "description": "Minor flooding is occurring.\nDo not drive through flooded roads."
+    }]}"#;
+    let mut alerts = serde_json::from_str::<OneCallAlerts>(json).unwrap().alerts;
+    let offset = UtcOffset::from_hms(-5, 0, 0).unwrap();
+    assert_snapshot(
+        "alert_banner",
+        &render!(alert_banner(
+            alerts.remove(0),
+            offset,
+            rsx!(button { "Dismiss" })
+        )),
+    );
+}
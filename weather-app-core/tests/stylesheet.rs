@@ -0,0 +1,85 @@
+use std::{collections::BTreeSet, fs, path::Path};
+
+use weather_app_core::assets::TAILWIND_CSS;
+
+/// Classes that never matched anything in the tailwind 2 CDN build either
+static IGNORED: [&str; 2] = ["divider", "dark:bg-gray-600"];
+
+/// Sources of the crates rendering with the bundled stylesheet, relative to
+/// this crate.
+static SOURCES: [&str; 3] = ["src", "../src", "../weather-app-desktop/src"];
+
+fn rust_files(dir: &Path, files: &mut Vec<String>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            rust_files(&path, files);
+        } else if path.extension().is_some_and(|e| e == "rs") {
+            files.push(fs::read_to_string(&path).unwrap());
+        }
+    }
+}
+
+fn string_literals(s: &str) -> impl Iterator<Item = &str> {
+    s.split('"').skip(1).step_by(2)
+}
+
+/// Collect the class names from `class: "..."` attributes and from the
+/// literals assigned to `let class = ...;` bindings.
+fn used_classes(source: &str, classes: &mut BTreeSet<String>) {
+    let mut names = Vec::new();
+    for (i, _) in source.match_indices("class: \"") {
+        names.extend(string_literals(&source[i..]).next());
+    }
+    for (i, _) in source.match_indices("let class = ") {
+        let binding = &source[i..];
+        let end = binding.find(';').unwrap_or(binding.len());
+        names.extend(string_literals(&binding[..end]));
+    }
+    for name in names.iter().flat_map(|n| n.split_whitespace()) {
+        if !name.contains('{') {
+            classes.insert(name.to_string());
+        }
+    }
+}
+
+/// `sm:w-1/2` is written `.sm\:w-1\/2` in the stylesheet
+fn selector(class: &str) -> String {
+    let mut selector = String::from(".");
+    for c in class.chars() {
+        if matches!(c, ':' | '/' | '.') {
+            selector.push('\\');
+        }
+        selector.push(c);
+    }
+    selector
+}
+
+#[test]
+fn test_stylesheet_covers_used_classes() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut files = Vec::new();
+    for dir in SOURCES {
+        rust_files(&root.join(dir), &mut files);
+    }
+    let mut classes = BTreeSet::new();
+    for file in &files {
+        used_classes(file, &mut classes);
+    }
+    assert!(classes.contains("w-1/2"));
+
+    let missing: Vec<_> = classes
+        .iter()
+        .filter(|c| !IGNORED.contains(&c.as_str()))
+        .filter(|c| {
+            let selector = selector(c);
+            ![" ", ":", ",", "{"]
+                .iter()
+                .any(|end| TAILWIND_CSS.contains(&format!("{selector}{end}")))
+        })
+        .collect();
+    assert!(
+        missing.is_empty(),
+        "classes missing from assets/tailwind.css, add their tailwind 2 rules: {missing:?}"
+    );
+}
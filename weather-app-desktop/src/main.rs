@@ -2,22 +2,35 @@
 #![allow(clippy::too_many_lines)]
 
 use anyhow::{format_err, Error};
+use async_trait::async_trait;
 use dioxus::prelude::{
-    dioxus_elements, format_args_f, rsx, use_state, Element, LazyNodes, NodeFactory, Scope, VNode,
+    dioxus_elements, format_args_f, rsx, use_coroutine, use_state, Element, LazyNodes,
+    NodeFactory, Scope, UnboundedReceiver, VNode,
 };
+use futures_util::{stream, StreamExt};
 use lazy_static::lazy_static;
-use log::debug;
+use log::{debug, error};
 use parking_lot::RwLock;
+use reqwest::{Client, Response};
 use stack_string::{format_sstr, StackString};
-use std::collections::HashMap;
-use time::{format_description::well_known::Rfc3339, UtcOffset};
-use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
-
-use weather_util_rust::{
-    config::Config,
-    weather_api::{WeatherApi, WeatherLocation},
-    weather_data::WeatherData,
-    weather_forecast::WeatherForecast,
+use std::{collections::HashMap, env, fs, path::PathBuf};
+use time::{OffsetDateTime, UtcOffset};
+use url::Url;
+
+use weather_util_rust::{config::Config, weather_api::WeatherLocation, ApiStringType};
+
+use weather_app_core::{
+    alerts::{active_alerts, alert_banner, OneCallAlerts, WeatherAlert, MAX_DISMISSED_ALERTS},
+    assets::TAILWIND_CSS,
+    components::{page_layout, weather_card, ForecastView},
+    current::CurrentWeather,
+    endpoint::resolve_geocode_path,
+    entry::{RequestState, WeatherEntry},
+    error::{ParseError, WeatherError},
+    hourly::HourlyForecast,
+    location::{get_parameters, parse_location},
+    provider::{fetch_entry, GeoLocation, WeatherProvider},
+    units::{PrecipitationUnit, SpeedUnit, TemperatureUnit, UnitSystem},
 };
 
 lazy_static! {
@@ -25,376 +38,696 @@ lazy_static! {
 }
 
 static DEFAULT_STR: &str = "11106";
+static DEFAULT_API_ENDPOINT: &str = "api.openweathermap.org";
+static DEFAULT_API_PATH: &str = "data/2.5/";
 
-#[derive(Clone, Debug)]
-struct WeatherEntry {
-    weather: Option<WeatherData>,
-    forecast: Option<WeatherForecast>,
+struct WeatherCache {
+    entries: RwLock<HashMap<StackString, WeatherEntry>>,
+    states: RwLock<HashMap<StackString, RequestState>>,
 }
 
-struct WeatherCache(RwLock<HashMap<StackString, WeatherEntry>>);
-
 impl WeatherCache {
     fn new() -> Self {
-        Self(RwLock::new(HashMap::new()))
+        Self {
+            entries: RwLock::new(HashMap::new()),
+            states: RwLock::new(HashMap::new()),
+        }
     }
 
-    fn update(&self, msg: &str, weather: Option<WeatherData>, forecast: Option<WeatherForecast>) {
-        self.0
-            .write()
-            .insert(msg.into(), WeatherEntry { weather, forecast });
+    fn update(&self, msg: &str, entry: WeatherEntry) {
+        let state = if entry.weather.is_some() && entry.forecast.is_some() {
+            RequestState::Loaded
+        } else {
+            RequestState::Failed
+        };
+        self.entries.write().insert(msg.into(), entry);
+        self.set_state(msg, state);
+        if let Err(e) = self.save() {
+            error!("Failed to save weather cache {e}");
+        }
+    }
+
+    /// Load the last known weather for each search string from disk
+    fn load(&self) -> Result<(), Error> {
+        let path = get_config_path("weather_cache.json");
+        if path.exists() {
+            let entries: HashMap<StackString, WeatherEntry> =
+                serde_json::from_slice(&fs::read(&path)?)?;
+            self.entries.write().extend(entries);
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        let path = get_config_path("weather_cache.json");
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let entries: HashMap<StackString, WeatherEntry> = self
+            .entries
+            .read()
+            .iter()
+            .filter(|(_, e)| e.weather.is_some() && e.forecast.is_some())
+            .map(|(k, e)| (k.clone(), e.clone()))
+            .collect();
+        fs::write(&path, serde_json::to_vec(&entries)?)?;
+        Ok(())
     }
 
     fn contains_key(&self, key: &str) -> bool {
-        self.0.read().contains_key(key)
+        self.entries.read().contains_key(key)
     }
 
     fn get_entry(&self, key: &str) -> Option<WeatherEntry> {
-        self.0.read().get(key).map(Clone::clone)
+        self.entries.read().get(key).cloned()
+    }
+
+    fn set_state(&self, key: &str, state: RequestState) {
+        self.states.write().insert(key.into(), state);
+    }
+
+    fn get_state(&self, key: &str) -> RequestState {
+        self.states
+            .read()
+            .get(key)
+            .copied()
+            .unwrap_or(RequestState::Idle)
     }
 }
 
-fn main() -> Result<(), Error> {
+fn main() {
     env_logger::init();
-    let (send, mut recv) = unbounded_channel::<StackString>();
-    let config = Config::init_config(None)?;
-    let api_key = config
-        .api_key
-        .as_ref()
-        .ok_or_else(|| format_err!("No api key given"))?;
-    let api = WeatherApi::new(api_key.as_str(), &config.api_endpoint, &config.api_path);
-
-    let handle: std::thread::JoinHandle<Result<(), Error>> = std::thread::spawn(move || {
-        tokio::runtime::Builder::new_multi_thread()
-            .enable_all()
-            .build()?
-            .block_on(async move {
-                while let Some(msg) = recv.recv().await {
-                    debug!("grab {msg} weather");
-                    let loc = get_parameters(&msg);
-                    let weather = api.get_weather_data(&loc).await.ok();
-                    let forecast = api.get_weather_forecast(&loc).await.ok();
-                    WEATHER_CACHE.update(&msg, weather, forecast);
-                }
-            });
-        Ok(())
+    let config = Config::init_config(None).unwrap_or_else(|e| {
+        error!("Failed to load config {e}");
+        Config::new()
     });
+    if let Err(e) = WEATHER_CACHE.load() {
+        error!("Failed to load weather cache {e}");
+    }
+    let units = get_unit_system().unwrap_or_default();
+    let dismissed_alerts = get_dismissed_alerts().unwrap_or_else(|e| {
+        error!("Failed to load dismissed alerts {e}");
+        Vec::new()
+    });
+    let props = AppProps {
+        config,
+        units,
+        dismissed_alerts,
+    };
+
+    dioxus::desktop::launch_with_props(app, props, |c| {
+        c.with_custom_head(format!("<style>{TAILWIND_CSS}</style>"))
+    });
+}
+
+/// Everything `app` would otherwise read from disk, so it can be rendered
+/// without the user's config
+struct AppProps {
+    config: Config,
+    units: UnitSystem,
+    dismissed_alerts: Vec<StackString>,
+}
 
-    send.send(DEFAULT_STR.into())?;
-    loop {
-        if WEATHER_CACHE.contains_key(DEFAULT_STR) {
-            break;
+enum WeatherRequest {
+    Fetch(StackString),
+    SetApiKey(StackString),
+}
+
+/// `WeatherApi` only returns the parsed `WeatherData` and `WeatherForecast`,
+/// which drop the cloud cover and the wind and precipitation probability of
+/// each slot, and has no geocoding, so the api is requested directly.
+struct Api {
+    client: Client,
+    api_key: StackString,
+    api_endpoint: StackString,
+    api_path: StackString,
+    geocode_path: StackString,
+}
+
+impl Api {
+    fn new(config: &Config, api_key: &str) -> Self {
+        let api_endpoint = if config.api_endpoint.is_empty() {
+            DEFAULT_API_ENDPOINT
+        } else {
+            config.api_endpoint.as_str()
+        };
+        let api_path = if config.api_path.is_empty() {
+            DEFAULT_API_PATH
+        } else {
+            config.api_path.as_str()
+        };
+        // Loaded from config.env along with the rest of `Config`
+        let geocode_path = env::var("GEOCODE_PATH").ok();
+        Self {
+            client: Client::new(),
+            api_key: api_key.into(),
+            api_endpoint: api_endpoint.into(),
+            api_path: api_path.into(),
+            geocode_path: resolve_geocode_path([geocode_path.as_deref()]),
         }
-        std::thread::sleep(std::time::Duration::from_millis(10));
     }
 
-    dioxus::desktop::launch_with_props(app, AppProps { send }, |c| c);
-    handle.join().unwrap()?;
-    Ok(())
+    async fn run_api<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        mut options: Vec<(&'static str, ApiStringType)>,
+    ) -> Result<T, WeatherError> {
+        options.push(("APPID", self.api_key.as_str().into()));
+        let base_url = format_sstr!("https://{}/{path}", self.api_endpoint);
+        let url = Url::parse_with_params(&base_url, &options).map_err(WeatherError::network)?;
+        self.client
+            .get(url)
+            .send()
+            .await
+            .and_then(Response::error_for_status)
+            .map_err(reqwest_error)?
+            .json()
+            .await
+            .map_err(reqwest_error)
+    }
 }
 
-struct AppProps {
-    send: UnboundedSender<StackString>,
+#[async_trait(?Send)]
+impl WeatherProvider for Api {
+    async fn weather(&self, location: &WeatherLocation) -> Result<CurrentWeather, WeatherError> {
+        let path = format_sstr!("{}weather", self.api_path);
+        self.run_api(&path, location.get_options()).await
+    }
+
+    async fn forecast(&self, location: &WeatherLocation) -> Result<HourlyForecast, WeatherError> {
+        let path = format_sstr!("{}forecast", self.api_path);
+        self.run_api(&path, location.get_options()).await
+    }
+
+    async fn geocode(&self, query: &str) -> Result<Vec<GeoLocation>, WeatherError> {
+        let options = vec![("q", query.into()), ("limit", "5".into())];
+        self.run_api(&self.geocode_path, options).await
+    }
+
+    async fn alerts(&self, location: &WeatherLocation) -> Result<Vec<WeatherAlert>, WeatherError> {
+        let path = format_sstr!("{}onecall", self.api_path);
+        let mut options = location.get_options();
+        options.push(("exclude", "current,minutely,hourly,daily".into()));
+        let one_call: OneCallAlerts = self.run_api(&path, options).await?;
+        Ok(one_call.alerts)
+    }
+}
+
+fn reqwest_error(e: reqwest::Error) -> WeatherError {
+    match e.status() {
+        Some(status) => WeatherError::HttpStatus(status.as_u16()),
+        None if e.is_decode() => WeatherError::json_decode(e),
+        None => WeatherError::network(e),
+    }
 }
 
 fn app(cx: Scope<AppProps>) -> Element {
-    let (search_str, set_search_str) = use_state(&cx, StackString::new).split();
-    let (weather_default, forecast_default) = {
-        let WeatherEntry { weather, forecast } = WEATHER_CACHE.get_entry(DEFAULT_STR).unwrap();
-        (weather.unwrap(), forecast.unwrap())
-    };
-    let (weather, set_weather) = use_state(&cx, || weather_default).split();
-    let (forecast, set_forecast) = use_state(&cx, || forecast_default).split();
-    let (draft, set_draft) = use_state(&cx, || search_str.clone()).split();
+    let (search_str, set_search_str) = use_state(&cx, || StackString::from(DEFAULT_STR)).split();
+    let (entry, set_entry) = use_state(&cx, || WEATHER_CACHE.get_entry(DEFAULT_STR)).split();
+    let (forecast_view, set_forecast_view) = use_state(&cx, ForecastView::default).split();
+    let (draft, set_draft) = use_state(&cx, StackString::new).split();
     let (search_history, set_search_history) =
         use_state(&cx, || vec![StackString::from(DEFAULT_STR)]).split();
+    let (units, set_units) = use_state(&cx, || cx.props.units).split();
+    let (error, set_error) = use_state(&cx, || None::<StackString>).split();
+    let (dismissed_alerts, set_dismissed_alerts) =
+        use_state(&cx, || cx.props.dismissed_alerts.clone()).split();
+    let (show_settings, set_show_settings) =
+        use_state(&cx, || cx.props.config.api_key.is_none()).split();
+    let (api_key_draft, set_api_key_draft) = use_state(&cx, StackString::new).split();
 
-    cx.render({
-        let country_info_element = country_info(&weather);
-        let country_data_element = country_data(&weather);
-        let week_weather_element = week_weather(&forecast);
-
-        rsx! {
-            link { rel: "stylesheet", href: "https://unpkg.com/tailwindcss@^2.0/dist/tailwind.min.css" },
-            div { class: "mx-auto p-4 bg-gray-100 h-screen flex justify-center",
-                div { class: "flex items-center justify-center flex-col",
-                    div {
-                        div { class: "inline-flex flex-col justify-center relative text-gray-500",
-                            div { class: "relative",
-                                input { class: "p-2 pl-8 rounded border border-gray-200 bg-gray-200 focus:bg-white focus:outline-none focus:ring-2 focus:ring-yellow-600 focus:border-transparent",
-                                    placeholder: "search...",
-                                    "type": "text",
-                                    value: "{draft}",
-                                    oninput: move |evt| {
-                                        let msg = evt.value.as_str();
-                                        set_draft.modify(|_| evt.value.as_str().into());
-                                        set_draft.needs_update();
-                                        if let Some(WeatherEntry{weather, forecast}) = WEATHER_CACHE.get_entry(msg) {
-                                            if let Some(weather) = weather {
-                                                set_weather.modify(|_| weather);
-                                                set_weather.needs_update();
-                                            }
-                                            if let Some(forecast) = forecast {
-                                                set_forecast.modify(|_| forecast);
-                                                set_forecast.needs_update();
-                                            }
-                                        }
-                                    },
-                                    onkeydown: move |evt| {
-                                        if let Some(WeatherEntry{weather, forecast}) = WEATHER_CACHE.get_entry(draft) {
-                                            if let Some(weather) = weather {
-                                                set_weather.modify(|_| weather);
-                                                set_weather.needs_update();
-                                            }
-                                            if let Some(forecast) = forecast {
-                                                set_forecast.modify(|_| forecast);
-                                                set_forecast.needs_update();
-                                            }
-                                        }
-                                        if evt.key == "Enter" {
-                                            set_search_str.modify(|_| draft.clone());
-                                            set_search_str.needs_update();
-                                            cx.props.send.send(draft.clone()).unwrap();
-                                            set_draft.modify(|_| "".into());
-                                            set_draft.needs_update();
-                                            loop {
-                                                if let Some(WeatherEntry{weather, forecast}) = WEATHER_CACHE.get_entry(draft) {
-                                                    if let Some(weather) = weather {
-                                                        set_weather.modify(|_| weather);
-                                                        set_weather.needs_update();
-                                                    } else {
-                                                        break;
-                                                    }
-                                                    if let Some(forecast) = forecast {
-                                                        set_forecast.modify(|_| forecast);
-                                                        set_forecast.needs_update();
-                                                    } else {
-                                                        break;
-                                                    }
-                                                    set_search_history.modify(|sh| {
-                                                        let mut v: Vec<_> = sh.iter().filter(|s| *s != draft).cloned().collect();
-                                                        v.push(draft.clone());
-                                                        v
-                                                    });
-                                                    set_search_history.needs_update();
-                                                    break;
-                                                }
-                                                std::thread::sleep(std::time::Duration::from_millis(10));
-                                            }
-                                        }
-                                    },
-                                }
-                                svg { class: "w-4 h-4 absolute left-2.5 top-3.5",
-                                    "viewBox": "0 0 24 24",
-                                    fill: "none",
-                                    stroke: "currentColor",
-                                    xmlns: "http://www.w3.org/2000/svg",
-                                    path {
-                                        d: "M21 21l-6-6m2-5a7 7 0 11-14 0 7 7 0 0114 0z",
-                                        "stroke-linejoin": "round",
-                                        "stroke-linecap": "round",
-                                        "stroke-width": "2",
-                                    }
-                                }
-                            }
+    let weather_task = use_coroutine(&cx, |rx: UnboundedReceiver<WeatherRequest>| {
+        let config = cx.props.config.clone();
+        let set_search_str = set_search_str.clone();
+        let set_entry = set_entry.clone();
+        let set_search_history = set_search_history.clone();
+        let set_error = set_error.clone();
+        let mut api = config.api_key.as_ref().map(|k| Api::new(&config, k));
+        let mut initial = Vec::new();
+        if api.is_some() {
+            WEATHER_CACHE.set_state(DEFAULT_STR, RequestState::Loading);
+            initial.push(WeatherRequest::Fetch(DEFAULT_STR.into()));
+        }
+        async move {
+            let mut requests = stream::iter(initial).chain(rx);
+            while let Some(request) = requests.next().await {
+                let msg = match request {
+                    WeatherRequest::Fetch(msg) => msg,
+                    WeatherRequest::SetApiKey(api_key) => {
+                        api.replace(Api::new(&config, &api_key));
+                        continue;
+                    }
+                };
+                let current = *set_search_str.current() == msg;
+                debug!("grab {msg} weather");
+                let result = match &api {
+                    Some(api) => {
+                        let entry = fetch_entry(api, &get_parameters(&msg)).await;
+                        match entry.error.clone() {
+                            Some(e) => Err(e.into()),
+                            None => Ok(entry),
                         }
-                        select { class: "bg-white border border-gray-100 w-full mt-2",
-                            id: "history-selector",
-                            onchange: move |x| {
-                                let s = x.data.value.as_str();
-                                if let Some(WeatherEntry{weather, forecast}) = WEATHER_CACHE.get_entry(s) {
-                                    if let Some(weather) = weather {
-                                        set_weather.modify(|_| weather);
-                                        set_weather.needs_update();
-                                    }
-                                    if let Some(forecast) = forecast {
-                                        set_forecast.modify(|_| forecast);
-                                        set_forecast.needs_update();
-                                    }
-                                }
-                                set_search_history.modify(|sh| {
-                                    let mut v: Vec<_> = sh.iter().filter(|x| x.as_str() != s).cloned().collect();
-                                    v.push(s.into());
-                                    v
-                                });
-                                set_search_history.needs_update();
-                            },
-                            {search_history.iter().rev().map(|s| {
-                                let selected = WEATHER_CACHE.contains_key(s);
-                                rsx! {
-                                    option { class: "pl-8 pr-2 py-1 border-b-2 border-gray-100 relative cursor-pointer hover:bg-yellow-50 hover:text-gray-900",
-                                        key: "search-history-key-{s}",
-                                        value: "{s}",
-                                        selected: "{selected}",
-                                        "{s}"
-                                    }
-                                }
-                            })}
+                    }
+                    None => Err(format_err!("No api key given")),
+                };
+                match result {
+                    Ok(entry) => {
+                        WEATHER_CACHE.update(&msg, entry.clone());
+                        if current {
+                            set_entry.set(Some(entry));
+                            set_error.set(None);
                         }
+                        set_search_history.modify(|sh| {
+                            let mut v: Vec<_> = sh.iter().filter(|s| **s != msg).cloned().collect();
+                            v.push(msg.clone());
+                            v
+                        });
                     }
-                    div { class: "flex flex-wrap w-full px-2",
-                        div { class: "bg-gray-900 text-white relative min-w-0 break-words rounded-lg overflow-hidden shadow-sm mb-4 w-full bg-white dark:bg-gray-600",
-                            div { class: "px-6 py-6 relative",
-                                country_info_element,
-                                country_data_element,
-                            }
-                            week_weather_element,
+                    Err(e) => {
+                        error!("{msg} {e}");
+                        WEATHER_CACHE.set_state(&msg, RequestState::Failed);
+                        if current {
+                            set_entry.set(WEATHER_CACHE.get_entry(&msg));
+                            set_error.set(Some(format_sstr!("{msg}: {e}")));
                         }
                     }
                 }
             }
         }
-        }
-    )
-}
+    });
 
-fn country_data<'a>(weather: &'a WeatherData) -> LazyNodes<'a, 'a> {
-    let temp = weather.main.temp.fahrenheit();
-    let feels = weather.main.feels_like.fahrenheit();
-    let min = weather.main.temp_min.fahrenheit();
-    let max = weather.main.temp_max.fahrenheit();
-
-    rsx!(
-        div { class: "block sm:flex justify-between items-center flex-wrap",
-            div { class: "w-full sm:w-1/2",
-                div { class: "flex mb-2 justify-between items-center",
-                    span { "Temp" }
-                    small { class: "px-2 inline-block", "{temp:0.2}°F" }
+    cx.render({
+        let request_state = WEATHER_CACHE.get_state(search_str);
+        let loading = request_state == RequestState::Loading;
+        debug!("request_state {search_str} {request_state:?}");
+        let error_element = error.as_ref().map(|e| {
+            rsx! {
+                div { class: "flex w-full px-2",
+                    div { class: "bg-red-100 border border-red-400 text-red-700 px-4 py-3 rounded relative mb-4 w-full flex justify-between items-center",
+                        span { "{e}" }
+                        button { class: "ml-4 px-2 py-1 rounded bg-red-700 text-white",
+                            onclick: move |_| {
+                                WEATHER_CACHE.set_state(search_str, RequestState::Loading);
+                                weather_task.send(WeatherRequest::Fetch(search_str.clone()));
+                                set_error.set(None);
+                            },
+                            "Retry"
+                        }
+                    }
                 }
             }
-            div { class: "w-full sm:w-1/2",
-                div { class: "flex mb-2 justify-between items-center",
-                    span { "Feels like" }
-                    small { class: "px-2 inline-block", "{feels:0.2}°F" }
+        });
+        // Alerts for the location on the card, in that location's timezone
+        let alerts = entry.as_ref().map_or_else(Vec::new, |e| {
+            active_alerts(&e.alerts, dismissed_alerts, OffsetDateTime::now_utc())
+        });
+        let alerts_offset: UtcOffset = entry
+            .as_ref()
+            .and_then(|e| e.weather.as_ref())
+            .map_or(UtcOffset::UTC, |w| w.timezone.into());
+        let alerts_element = alerts.into_iter().map(move |alert| {
+            let id = alert.id();
+            let key = id.clone();
+            let dismiss = rsx! {
+                button { class: "ml-4 px-2 py-1 rounded bg-gray-700 text-white",
+                    onclick: move |_| {
+                        set_dismissed_alerts.modify(|d| {
+                            let mut d = d.clone();
+                            d.push(id.clone());
+                            d.drain(..d.len().saturating_sub(MAX_DISMISSED_ALERTS));
+                            if let Err(e) = store_dismissed_alerts(&d) {
+                                error!("Failed to save dismissed alerts {e}");
+                            }
+                            d
+                        });
+                        set_dismissed_alerts.needs_update();
+                    },
+                    "Dismiss"
                 }
+            };
+            let banner = alert_banner(alert, alerts_offset, dismiss);
+            rsx! {
+                div { key: "alert-key-{key}", banner }
             }
-            div { class: "w-full sm:w-1/2",
-                div { class: "flex mb-2 justify-between items-center",
-                    span { "Temp min" }
-                    small { class: "px-2 inline-block", "{min:0.2}°F" }
+        });
+        let view_tabs = ForecastView::ALL.iter().map(|view| {
+            let view = *view;
+            let label = view.to_str();
+            let class = if view == *forecast_view {
+                "px-3 py-1 mx-1 rounded bg-gray-700 text-white"
+            } else {
+                "px-3 py-1 mx-1 rounded text-gray-300"
+            };
+            rsx! {
+                button {
+                    key: "forecast-view-{label}",
+                    class: "{class}",
+                    onclick: move |_| set_forecast_view.set(view),
+                    "{label}"
                 }
             }
-            div { class: "w-full sm:w-1/2",
-                div { class: "flex mb-2 justify-between items-center",
-                    span { "Temp max" }
-                    small { class: "px-2 inline-block", "{max:0.2}°F" }
+        }).collect();
+        let card_element = weather_card(entry.as_ref(), *units, *forecast_view, loading, view_tabs);
+        let settings_element = if *show_settings {
+            Some(rsx! {
+                div { class: "flex w-full px-2",
+                    div { class: "bg-white border border-gray-200 px-4 py-3 rounded relative mb-4 w-full flex justify-between items-center",
+                        input { class: "p-2 rounded border border-gray-200 bg-gray-200 focus:bg-white focus:outline-none w-full",
+                            placeholder: "openweathermap api key",
+                            "type": "password",
+                            value: "{api_key_draft}",
+                            oninput: move |evt| {
+                                set_api_key_draft.modify(|_| evt.value.as_str().into());
+                                set_api_key_draft.needs_update();
+                            },
+                        }
+                        button { class: "ml-4 px-2 py-1 rounded bg-gray-700 text-white",
+                            onclick: move |_| {
+                                if api_key_draft.is_empty() {
+                                    return;
+                                }
+                                if let Err(e) = set_api_key(api_key_draft) {
+                                    error!("Failed to save api key {e}");
+                                }
+                                weather_task.send(WeatherRequest::SetApiKey(api_key_draft.clone()));
+                                WEATHER_CACHE.set_state(search_str, RequestState::Loading);
+                                weather_task.send(WeatherRequest::Fetch(search_str.clone()));
+                                set_api_key_draft.set(StackString::new());
+                                set_show_settings.set(false);
+                                set_error.set(None);
+                            },
+                            "Save"
+                        }
+                    }
                 }
-            }
-        }
-    )
-}
+            })
+        } else {
+            None
+        };
 
-fn country_info<'a>(weather: &'a WeatherData) -> LazyNodes<'a, 'a> {
-    let name = &weather.name;
-    let country = weather.sys.country.as_ref().map_or("", StackString::as_str);
-    let mut main = StackString::new();
-    let mut desc = StackString::new();
-    let mut icon = StackString::new();
-    if let Some(weather) = weather.weather.get(0) {
-        main.push_str(&weather.main);
-        desc.push_str(&weather.description);
-        icon.push_str(&weather.icon);
-    }
-    let temp = weather.main.temp.fahrenheit();
-    let fo: UtcOffset = weather.timezone.into();
-    let date = weather.dt.to_offset(fo).format(&Rfc3339).unwrap();
-
-    rsx!(
-        div { class: "flex mb-4 justify-between items-center",
-            div {
-                h5 { class: "mb-0 font-medium text-xl",
-                    "{name} {country}"
-                }
-                small {
-                    img { class: "block w-8 h-8",
-                        src: "http://openweathermap.org/img/wn/{icon}@2x.png",
+        let parse_hint = match parse_location(draft) {
+            Err(ParseError::Empty) | Ok(_) => None,
+            Err(e) => Some(rsx! {
+                span { class: "text-xs text-yellow-700 mt-1", "{e}, searching by city name" }
+            }),
+        };
+
+        let controls = rsx! {
+            div { class: "inline-flex flex-col justify-center relative text-gray-500",
+                div { class: "relative",
+                    input { class: "p-2 pl-8 rounded border border-gray-200 bg-gray-200 focus:bg-white focus:outline-none focus:ring-2 focus:ring-yellow-600 focus:border-transparent",
+                        placeholder: "search...",
+                        "type": "text",
+                        value: "{draft}",
+                        disabled: "{loading}",
+                        oninput: move |evt| {
+                            let msg = evt.value.as_str();
+                            set_draft.modify(|_| evt.value.as_str().into());
+                            set_draft.needs_update();
+                            if let Some(entry) = WEATHER_CACHE.get_entry(msg) {
+                                set_entry.set(Some(entry));
+                            }
+                        },
+                        onkeydown: move |evt| {
+                            if let Some(entry) = WEATHER_CACHE.get_entry(draft) {
+                                set_entry.set(Some(entry));
+                            }
+                            if evt.key == "Enter" {
+                                set_entry.set(WEATHER_CACHE.get_entry(draft));
+                                WEATHER_CACHE.set_state(draft, RequestState::Loading);
+                                set_search_str.modify(|_| draft.clone());
+                                set_search_str.needs_update();
+                                weather_task.send(WeatherRequest::Fetch(draft.clone()));
+                                set_draft.modify(|_| "".into());
+                                set_draft.needs_update();
+                            }
+                        },
                     }
-                }
-            }
-            div { class: "text-right",
-                h6 { class: "mb-0",
-                    "{date}"
-                }
-                h3 { class: "font-bold text-4xl mb-0",
-                    span {
-                        "{temp:0.1}°F"
+                    svg { class: "w-4 h-4 absolute left-2.5 top-3.5",
+                        "viewBox": "0 0 24 24",
+                        fill: "none",
+                        stroke: "currentColor",
+                        xmlns: "http://www.w3.org/2000/svg",
+                        path {
+                            d: "M21 21l-6-6m2-5a7 7 0 11-14 0 7 7 0 0114 0z",
+                            "stroke-linejoin": "round",
+                            "stroke-linecap": "round",
+                            "stroke-width": "2",
+                        }
                     }
                 }
+                parse_hint,
             }
-        }
-    )
-}
-
-fn week_weather<'a>(forecast: &'a WeatherForecast) -> LazyNodes<'a, 'a> {
-    let high_low = forecast.get_high_low();
-    rsx!(
-        div { class: "divider table mx-2 text-center bg-transparent whitespace-nowrap",
-            span { class: "inline-block px-3", small { "Forecast" } }
-        }
-        div { class: "px-6 py-6 relative",
-            div { class: "text-center justify-between items-center flex",
-                style: "flex-flow: initial;",
-                high_low.iter().map(|(d, (h, l, r, s, i))| {
-                    let weekday = d.weekday();
-                    let low = l.fahrenheit();
-                    let high = h.fahrenheit();
-                    let mut rain = StackString::new();
-                    let mut snow = StackString::new();
-                    if r.millimeters() > 0.0 {
-                        rain = format_sstr!("R {:0.1}\"", r.inches());
-                    }
-                    if s.millimeters() > 0.0 {
-                        snow = format_sstr!("S {:0.1}\"", s.inches());
+            select { class: "bg-white border border-gray-100 w-full mt-2",
+                id: "history-selector",
+                onchange: move |x| {
+                    let s = x.data.value.as_str();
+                    if let Some(entry) = WEATHER_CACHE.get_entry(s) {
+                        set_entry.set(Some(entry));
                     }
-                    let mut icon = StackString::new();
-                    if let Some(i) = i.iter().next() {
-                        icon.push_str(i);
+                    set_search_history.modify(|sh| {
+                        let mut v: Vec<_> = sh.iter().filter(|x| x.as_str() != s).cloned().collect();
+                        v.push(s.into());
+                        v
+                    });
+                    set_search_history.needs_update();
+                    set_search_str.modify(|_| s.into());
+                    set_search_str.needs_update();
+                    set_error.set(None);
+                },
+                {search_history.iter().rev().map(|s| {
+                    let selected = WEATHER_CACHE.contains_key(s);
+                    rsx! {
+                        option { class: "pl-8 pr-2 py-1 border-b-2 border-gray-100 relative cursor-pointer hover:bg-yellow-50 hover:text-gray-900",
+                            key: "search-history-key-{s}",
+                            value: "{s}",
+                            selected: "{selected}",
+                            "{s}"
+                        }
                     }
-
-                    rsx!(div {
-                            key: "weather-forecast-key-{d}",
-                            class: "text-center mb-0 flex items-center justify-center flex-col",
-                            span { class: "block my-1",
-                                "{weekday}"
-                            }
-                            img { class: "block w-8 h-8",
-                                src: "http://openweathermap.org/img/wn/{icon}@2x.png",
-                            }
-                            span { class: "block my-1",
-                                "{low:0.1}F°"
-                            }
-                            span { class: "block my-1",
-                                "{high:0.1}F°"
+                })}
+            }
+            div { class: "flex w-full mt-2",
+                select { class: "bg-white border border-gray-100 w-full",
+                    id: "temperature-unit-selector",
+                    onchange: move |x| {
+                        if let Ok(temperature) = x.data.value.parse() {
+                            let new_units = UnitSystem { temperature, ..*units };
+                            set_unit_system(&new_units).unwrap_or_else(|e| debug!("{e:?}"));
+                            set_units.modify(|_| new_units);
+                            set_units.needs_update();
+                        }
+                    },
+                    {TemperatureUnit::ALL.iter().map(|u| {
+                        let selected = *u == units.temperature;
+                        let value = u.to_str();
+                        let suffix = u.suffix();
+                        rsx! {
+                            option {
+                                key: "temperature-unit-key-{value}",
+                                value: "{value}",
+                                selected: "{selected}",
+                                "{suffix}"
                             }
-                            span { class: "block my-1",
-                                "{rain}"
+                        }
+                    })}
+                }
+                select { class: "bg-white border border-gray-100 w-full",
+                    id: "precipitation-unit-selector",
+                    onchange: move |x| {
+                        if let Ok(precipitation) = x.data.value.parse() {
+                            let new_units = UnitSystem { precipitation, ..*units };
+                            set_unit_system(&new_units).unwrap_or_else(|e| debug!("{e:?}"));
+                            set_units.modify(|_| new_units);
+                            set_units.needs_update();
+                        }
+                    },
+                    {PrecipitationUnit::ALL.iter().map(|u| {
+                        let selected = *u == units.precipitation;
+                        let value = u.to_str();
+                        let suffix = u.suffix();
+                        rsx! {
+                            option {
+                                key: "precipitation-unit-key-{value}",
+                                value: "{value}",
+                                selected: "{selected}",
+                                "{suffix}"
                             }
-                            span { class: "block my-1",
-                                "{snow}"
+                        }
+                    })}
+                }
+                select { class: "bg-white border border-gray-100 w-full",
+                    id: "speed-unit-selector",
+                    onchange: move |x| {
+                        if let Ok(speed) = x.data.value.parse() {
+                            let new_units = UnitSystem { speed, ..*units };
+                            set_unit_system(&new_units).unwrap_or_else(|e| debug!("{e:?}"));
+                            set_units.modify(|_| new_units);
+                            set_units.needs_update();
+                        }
+                    },
+                    {SpeedUnit::ALL.iter().map(|u| {
+                        let selected = *u == units.speed;
+                        let value = u.to_str();
+                        let suffix = u.suffix();
+                        rsx! {
+                            option {
+                                key: "speed-unit-key-{value}",
+                                value: "{value}",
+                                selected: "{selected}",
+                                "{suffix}"
                             }
                         }
-                    )
-                })
+                    })}
+                }
+                button { class: "bg-white border border-gray-100 px-2",
+                    onclick: move |_| {
+                        set_show_settings.modify(|s| !s);
+                        set_show_settings.needs_update();
+                    },
+                    "Settings"
+                }
             }
+        };
+        let notices = [settings_element, error_element]
+            .into_iter()
+            .flatten()
+            .chain(alerts_element)
+            .collect();
+        page_layout(controls, notices, card_element, Vec::new())
         }
     )
 }
 
-fn get_parameters(search_str: &str) -> WeatherLocation {
-    let mut opts = WeatherLocation::from_city_name(search_str);
-    if let Ok(zip) = search_str.parse::<u64>() {
-        opts = WeatherLocation::from_zipcode(zip);
-    } else if search_str.contains(',') {
-        let mut iter = search_str.split(',');
-        if let Some(lat) = iter.next() {
-            if let Ok(lat) = lat.parse() {
-                if let Some(lon) = iter.next() {
-                    if let Ok(lon) = lon.parse() {
-                        opts = WeatherLocation::from_lat_lon(lat, lon);
-                    }
-                }
-            }
+fn get_config_path(fname: &str) -> PathBuf {
+    let config_dir = dirs::config_dir().unwrap_or_else(|| "./".into());
+    config_dir.join("weather_util").join(fname)
+}
+
+/// Store the api key in the same `config.env` that `Config::init_config` reads
+fn set_api_key(api_key: &str) -> Result<(), Error> {
+    let path = get_config_path("config.env");
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut lines: Vec<StackString> = if path.exists() {
+        fs::read_to_string(&path)?
+            .lines()
+            .filter(|l| !l.starts_with("API_KEY="))
+            .map(Into::into)
+            .collect()
+    } else {
+        Vec::new()
+    };
+    lines.push(format_sstr!("API_KEY={api_key}"));
+    fs::write(&path, lines.join("\n") + "\n")?;
+    Ok(())
+}
+
+fn set_unit_system(units: &UnitSystem) -> Result<(), Error> {
+    let path = get_config_path("units.json");
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_vec(units)?)?;
+    Ok(())
+}
+
+/// Dismissed alerts stay hidden across restarts, like in the web app
+fn store_dismissed_alerts(dismissed: &[StackString]) -> Result<(), Error> {
+    let path = get_config_path("dismissed_alerts.json");
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_vec(dismissed)?)?;
+    Ok(())
+}
+
+fn get_dismissed_alerts() -> Result<Vec<StackString>, Error> {
+    let path = get_config_path("dismissed_alerts.json");
+    if path.exists() {
+        serde_json::from_slice(&fs::read(&path)?).map_err(Into::into)
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+fn get_unit_system() -> Result<UnitSystem, Error> {
+    let path = get_config_path("units.json");
+    if path.exists() {
+        serde_json::from_slice(&fs::read(&path)?).map_err(Into::into)
+    } else {
+        Ok(UnitSystem::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dioxus::{core::VirtualDom, ssr};
+    use futures_util::FutureExt;
+    use std::{env, fs, path::Path};
+    use weather_util_rust::config::Config;
+
+    use weather_app_core::{
+        current::CurrentWeather,
+        hourly::HourlyForecast,
+        location::get_parameters,
+        provider::{fetch_entry, FixtureProvider},
+        units::UnitSystem,
+    };
+
+    use crate::{app, AppProps, DEFAULT_STR, WEATHER_CACHE};
+
+    /// Compare against `tests/snapshots/{name}.html`; run with
+    /// `UPDATE_SNAPSHOTS=1` to write new snapshots after an intended change.
+    fn assert_snapshot(name: &str, html: &str) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/snapshots")
+            .join(format!("{name}.html"));
+        if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            fs::write(&path, html).unwrap();
+            return;
         }
+        let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+            panic!("missing snapshot {path:?}, run with UPDATE_SNAPSHOTS=1 to create it")
+        });
+        assert!(
+            html == expected,
+            "snapshot {name} differs, run with UPDATE_SNAPSHOTS=1 to accept\n--- expected\n{expected}\n--- actual\n{html}"
+        );
+    }
+
+    /// The first render of `app` without an api key, with the fixture weather
+    /// cached for the default location.  Inserted directly so the cache isn't
+    /// saved to the user's config dir.
+    #[test]
+    fn test_app() {
+        let location = get_parameters(DEFAULT_STR);
+        let weather: CurrentWeather =
+            serde_json::from_str(include_str!("../../weather-app-core/tests/weather.json"))
+                .unwrap();
+        let forecast: HourlyForecast =
+            serde_json::from_str(include_str!("../../weather-app-core/tests/forecast.json"))
+                .unwrap();
+        let provider = FixtureProvider::new()
+            .with_weather(location.clone(), weather)
+            .with_forecast(location.clone(), forecast);
+        let entry = fetch_entry(&provider, &location)
+            .now_or_never()
+            .expect("FixtureProvider answers immediately");
+        WEATHER_CACHE
+            .entries
+            .write()
+            .insert(DEFAULT_STR.into(), entry);
+
+        let props = AppProps {
+            config: Config::new(),
+            units: UnitSystem::default(),
+            dismissed_alerts: Vec::new(),
+        };
+        let mut dom = VirtualDom::new_with_props(app, props);
+        let _ = dom.rebuild();
+        let html = ssr::render_vdom_cfg(&dom, |c| c.newline(true).indent(true)) + "\n";
+        assert_snapshot("app", &html);
     }
-    opts
 }
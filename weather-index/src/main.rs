@@ -12,6 +12,10 @@ use std::net::Ipv4Addr;
 use url::Url;
 use wasm_bindgen::{JsCast, JsValue};
 use wasm_bindgen_futures::JsFuture;
+use weather_app_core::{
+    location::{get_parameters, location_to_search},
+    route::{IndexRoute, IndexView},
+};
 use weather_util_rust::{latitude::Latitude, longitude::Longitude, weather_api::WeatherLocation};
 use web_sys::{window, Request, RequestInit, Response};
 
@@ -35,38 +39,41 @@ fn main() {
 }
 
 fn app(cx: Scope) -> Element {
-    let (url_path, set_url_path) = use_state(&cx, || "weather/plot.html").split();
+    let route = cx.use_hook(|_| get_route());
+    let (view, set_view) = use_state(&cx, || route.view.unwrap_or_default()).split();
     let (draft, set_draft) = use_state(&cx, String::new).split();
-    let (current_loc, set_current_loc) = use_state(&cx, || None).split();
     let (search_history, set_search_history) = use_state(&cx, || {
-        get_history().unwrap_or_else(|_| vec![String::from("zip=10001")])
+        let mut history = get_history().unwrap_or_else(|_| vec![String::from("zip=10001")]);
+        if let Some(loc) = &route.location {
+            // A stored `zip=10001` and a linked `10001` are the same location
+            if !history.iter().any(|x| get_parameters(x) == *loc) {
+                history.push(location_to_search(loc).to_string());
+                if let Err(e) = set_history(&history) {
+                    error!("Failed to set history {e:?}");
+                }
+            }
+        }
+        history
     })
     .split();
     let (ip_location, set_ip_location) = use_state(&cx, || get_parameters(DEFAULT_LOCATION)).split();
 
-    let location = use_read(&cx, LOCATION);
     let set_location = use_set(&cx, LOCATION);
+    cx.use_hook(|_| {
+        if let Some(loc) = &route.location {
+            set_location(loc.clone());
+        }
+    });
+    let location = use_read(&cx, LOCATION);
 
     let window = window().unwrap();
     let origin = window.location().origin().unwrap_or_else(|_| DEFAULT_URL.to_string());
-    let search = window.location().search().unwrap();
 
-    if !search.is_empty() && current_loc.is_none() {
-        let s = search.trim_start_matches("?location=").to_string();
-        let loc = get_parameters(&s);
-        set_current_loc.set(Some(s.clone()));
-        set_current_loc.needs_update();
-        if !search_history.contains(&s) {
-            set_search_history.modify(|sh| {
-                let mut v: Vec<String> = sh.iter().filter(|x| x.as_str() != s).cloned().collect();
-                v.push(s);
-                set_history(&v).expect("Failed to set history");
-                v
-            });
-            set_search_history.needs_update();
-            set_location(loc);
+    use_future(&cx, (location, view), |(location, view)| async move {
+        if let Err(e) = replace_route(&IndexRoute::new(&location, view)) {
+            debug!("replace route {e:?}");
         }
-    }
+    });
 
     let location_future = use_future(&cx, (), |_| async move {
         if let Ok(ip) = get_ip_address().await {
@@ -81,7 +88,7 @@ fn app(cx: Scope) -> Element {
 
     cx.render({
         let base_url = BASE_URL.unwrap_or(&origin);
-        let url: Url = format!("{base_url}/{url_path}").parse().expect("Failed to parse base url");
+        let url: Url = format!("{base_url}/{}", view.url_path()).parse().expect("Failed to parse base url");
         let url = Url::parse_with_params(url.as_str(), location.get_options()).unwrap_or(url);
         if let Some(Some(loc)) = location_future.value() {
             if loc != ip_location {
@@ -109,7 +116,7 @@ fn app(cx: Scope) -> Element {
                         name: "text",
                         value: "Text",
                         onclick: move |_| {
-                            set_url_path.modify(|_| "weather/index.html");
+                            set_view.set(IndexView::Text);
                         },
                     },
                     input {
@@ -117,7 +124,7 @@ fn app(cx: Scope) -> Element {
                         name: "plot",
                         value: "Plot",
                         onclick: move |_| {
-                            set_url_path.modify(|_| "weather/plot.html");
+                            set_view.set(IndexView::Plot);
                         },
                     },
                     input {
@@ -125,7 +132,7 @@ fn app(cx: Scope) -> Element {
                         name: "wasm",
                         value: "Wasm",
                         onclick: move |_| {
-                            set_url_path.modify(|_| "wasm_weather/index.html");
+                            set_view.set(IndexView::Wasm);
                         },
                     },
                     form {
@@ -157,8 +164,6 @@ fn app(cx: Scope) -> Element {
                                         set_search_history.needs_update();
                                     }
                                     set_location(loc);
-                                    set_current_loc.set(Some(draft.clone()));
-                                    set_current_loc.needs_update();
                                     set_draft.set(String::new());
                                     set_draft.needs_update();
                                 }
@@ -217,6 +222,29 @@ fn app(cx: Scope) -> Element {
     })
 }
 
+fn get_route() -> IndexRoute {
+    window()
+        .and_then(|w| w.location().search().ok())
+        .map(|search| IndexRoute::from_query(&search))
+        .unwrap_or_default()
+}
+
+/// Keeps the url in step with the location and view shown, without adding
+/// history entries.
+fn replace_route(route: &IndexRoute) -> Result<(), JsValue> {
+    let window = window().ok_or_else(|| JsValue::from_str("No window"))?;
+    let location = window.location();
+    let search = location.search()?;
+    let query = route.to_query(&search);
+    if query.as_str() == search {
+        return Ok(());
+    }
+    let url = format!("{}{query}{}", location.pathname()?, location.hash()?);
+    window
+        .history()?
+        .replace_state_with_url(&JsValue::NULL, "", Some(&url))
+}
+
 fn set_history(history: &[String]) -> Result<(), JsValue> {
     let window = window().ok_or_else(|| JsValue::from_str("No window"))?;
     let local_storage = window
@@ -245,8 +273,8 @@ fn get_history() -> Result<Vec<String>, JsValue> {
 }
 
 async fn js_fetch(url: &Url, method: Method) -> Result<JsValue, JsValue> {
-    let mut opts = RequestInit::new();
-    opts.method(method.as_str());
+    let opts = RequestInit::new();
+    opts.set_method(method.as_str());
 
     let request = Request::new_with_str_and_init(url.as_str(), &opts)?;
     let window = web_sys::window().unwrap();
@@ -256,8 +284,8 @@ async fn js_fetch(url: &Url, method: Method) -> Result<JsValue, JsValue> {
 }
 
 async fn text_fetch(url: &Url, method: Method) -> Result<JsValue, JsValue> {
-    let mut opts = RequestInit::new();
-    opts.method(method.as_str());
+    let opts = RequestInit::new();
+    opts.set_method(method.as_str());
 
     let request = Request::new_with_str_and_init(url.as_str(), &opts)?;
     let window = web_sys::window().unwrap();
@@ -313,22 +341,3 @@ async fn get_location_from_ip(ip: Ipv4Addr) -> Result<WeatherLocation, JsValue>
         location.longitude,
     ))
 }
-
-fn get_parameters(search_str: &str) -> WeatherLocation {
-    let mut opts = WeatherLocation::from_city_name(search_str);
-    if let Ok(zip) = search_str.parse::<u64>() {
-        opts = WeatherLocation::from_zipcode(zip);
-    } else if search_str.contains(',') {
-        let mut iter = search_str.split(',');
-        if let Some(lat) = iter.next() {
-            if let Ok(lat) = lat.parse() {
-                if let Some(lon) = iter.next() {
-                    if let Ok(lon) = lon.parse() {
-                        opts = WeatherLocation::from_lat_lon(lat, lon);
-                    }
-                }
-            }
-        }
-    }
-    opts
-}